@@ -0,0 +1,195 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::assets::{Manifest, ImageHandle, SoundHandle};
+
+/// File in the resources directory that defines every animation clip.
+pub const CLIPS_FILE: &str = "animations.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClipsFile {
+    clip: Vec<ClipDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClipDef {
+    name: String,
+    image: String,
+    #[serde(default = "default_looping")]
+    looping: bool,
+    frames: Vec<FrameDef>,
+    #[serde(default)]
+    events: Vec<EventDef>,
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrameDef {
+    /// x, y, width and height of the frame in the sprite sheet, in pixels.
+    rect: [u16; 4],
+    ticks: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EventDef {
+    frame: usize,
+    name: String,
+    sound: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipId(u16);
+
+#[derive(Debug)]
+pub struct Frame {
+    /// x, y, width and height in the sprite sheet, in pixels.
+    pub rect: [f32; 4],
+    pub ticks: u16,
+}
+
+/// Something a clip wants to happen when it reaches a certain frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub sound: Option<SoundHandle>,
+}
+
+#[derive(Debug)]
+pub struct Clip {
+    pub name: String,
+    pub image: ImageHandle,
+    pub looping: bool,
+    pub frames: Vec<Frame>,
+    events: Vec<(usize, AnimationEvent)>,
+}
+
+/// Every animation clip the game knows, by name.
+#[derive(Debug)]
+pub struct Clips {
+    clips: Vec<Clip>,
+}
+
+impl Clips {
+    pub fn load(resources_dir: &Path, manifest: &mut Manifest) -> Result<Clips, String> {
+        let path = resources_dir.join(CLIPS_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        Clips::parse(&text, manifest).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str, manifest: &mut Manifest) -> Result<Clips, String> {
+        let file: ClipsFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        let mut clips: Vec<Clip> = Vec::new();
+        for def in file.clip {
+            if clips.iter().any(|clip| clip.name == def.name) {
+                return Err(format!("clip `{}` is defined twice", def.name));
+            }
+            if def.frames.is_empty() {
+                return Err(format!("clip `{}` has no frames", def.name));
+            }
+            if def.frames.iter().any(|frame| frame.ticks == 0) {
+                return Err(format!("clip `{}` has a frame lasting 0 ticks", def.name));
+            }
+            let mut events = Vec::new();
+            for event in def.events {
+                if event.frame >= def.frames.len() {
+                    return Err(format!("clip `{}` has event `{}` on frame {}, but only {} frames", def.name, event.name, event.frame, def.frames.len()));
+                }
+                let sound = event.sound.as_deref().map(|path| manifest.sound(path));
+                events.push((event.frame, AnimationEvent { name: event.name, sound }));
+            }
+            clips.push(Clip {
+                image: manifest.image(&def.image),
+                looping: def.looping,
+                frames: def.frames.iter()
+                    .map(|frame| Frame { rect: frame.rect.map(f32::from), ticks: frame.ticks })
+                    .collect(),
+                events,
+                name: def.name,
+            });
+        }
+        Ok(Clips { clips })
+    }
+
+    pub fn id(&self, name: &str) -> Option<ClipId> {
+        self.clips.iter().position(|clip| clip.name == name).map(|index| ClipId(index as u16))
+    }
+
+    /// Like `id`, but for clips the game cannot do without.
+    pub fn require(&self, name: &str) -> Result<ClipId, String> {
+        self.id(name).ok_or_else(|| format!("{CLIPS_FILE} is missing the `{name}` clip"))
+    }
+
+    pub fn get(&self, id: ClipId) -> &Clip {
+        &self.clips[id.0 as usize]
+    }
+}
+
+/// Playback state of a clip on one entity.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub clip: ClipId,
+    pub frame: usize,
+    ticks_left: u16,
+    pub finished: bool,
+}
+
+impl Animation {
+    pub fn new(clip: ClipId, clips: &Clips) -> Animation {
+        Animation {
+            clip,
+            frame: 0,
+            ticks_left: clips.get(clip).frames[0].ticks,
+            finished: false,
+        }
+    }
+
+    /// Switches to `clip`, starting it from the beginning; keeps going if it
+    /// is already playing.
+    pub fn play(&mut self, clip: ClipId, clips: &Clips) {
+        if self.clip != clip {
+            *self = Animation::new(clip, clips);
+        }
+    }
+
+    /// Moves the animation on by one tick, returning the events of the frame
+    /// it entered, if any.
+    pub fn advance<'a>(&mut self, clips: &'a Clips) -> impl Iterator<Item = &'a AnimationEvent> {
+        let clip = clips.get(self.clip);
+        let mut entered = None;
+        if !self.finished {
+            self.ticks_left -= 1;
+            if self.ticks_left == 0 {
+                if self.frame + 1 < clip.frames.len() {
+                    self.frame += 1;
+                }
+                else if clip.looping {
+                    self.frame = 0;
+                }
+                else {
+                    self.finished = true;
+                }
+                self.ticks_left = clip.frames[self.frame].ticks;
+                if !self.finished {
+                    entered = Some(self.frame);
+                }
+            }
+        }
+        clip.events.iter()
+            .filter(move |(frame, _)| Some(*frame) == entered)
+            .map(|(_, event)| event)
+    }
+
+    pub fn current<'a>(&self, clips: &'a Clips) -> (&'a Clip, &'a Frame) {
+        let clip = clips.get(self.clip);
+        (clip, &clip.frames[self.frame])
+    }
+}
@@ -0,0 +1,77 @@
+use ggez::{
+    audio,
+    graphics,
+    Context,
+    GameResult,
+};
+
+/// Refers to an image in the `Assets` registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageHandle(u16);
+
+/// Refers to a sound in the `Assets` registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SoundHandle(u16);
+
+/// Every asset the game is going to need, by resource path. Building it does
+/// not touch the disk or the GPU, so the simulation can hand out handles in
+/// headless runs too; `Assets::load` then turns it into the real thing.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    images: Vec<String>,
+    sounds: Vec<String>,
+}
+
+impl Manifest {
+    pub fn new() -> Manifest {
+        Manifest::default()
+    }
+
+    /// Registers an image, returning the existing handle if it is already known.
+    pub fn image(&mut self, path: &str) -> ImageHandle {
+        ImageHandle(intern(&mut self.images, path))
+    }
+
+    /// Registers a sound, returning the existing handle if it is already known.
+    pub fn sound(&mut self, path: &str) -> SoundHandle {
+        SoundHandle(intern(&mut self.sounds, path))
+    }
+}
+
+fn intern(paths: &mut Vec<String>, path: &str) -> u16 {
+    match paths.iter().position(|known| known == path) {
+        Some(index) => index as u16,
+        None => {
+            paths.push(path.to_string());
+            (paths.len() - 1) as u16
+        }
+    }
+}
+
+/// All images and sounds, loaded once at startup.
+pub struct Assets {
+    images: Vec<graphics::Image>,
+    sounds: Vec<audio::Source>,
+}
+
+impl Assets {
+    pub fn load(ctx: &mut Context, manifest: &Manifest) -> GameResult<Assets> {
+        let mut images = Vec::with_capacity(manifest.images.len());
+        for path in &manifest.images {
+            images.push(graphics::Image::from_path(ctx, path)?);
+        }
+        let mut sounds = Vec::with_capacity(manifest.sounds.len());
+        for path in &manifest.sounds {
+            sounds.push(audio::Source::new(ctx, path)?);
+        }
+        Ok(Assets { images, sounds })
+    }
+
+    pub fn image(&self, handle: ImageHandle) -> &graphics::Image {
+        &self.images[handle.0 as usize]
+    }
+
+    pub fn sound(&mut self, handle: SoundHandle) -> &mut audio::Source {
+        &mut self.sounds[handle.0 as usize]
+    }
+}
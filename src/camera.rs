@@ -0,0 +1,45 @@
+use ggez::glam::Vec2;
+
+/// Which part of the world the window shows. Part of the simulation, since the
+/// mouse aims in window coordinates that only the camera turns into world ones.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    /// World position shown in the middle of the window.
+    pub center: Vec2,
+    /// Size of the window, in world pixels.
+    pub viewport: Vec2,
+    /// Size of the world; the camera never looks past its edges.
+    bounds: Vec2,
+}
+
+impl Camera {
+    pub fn new(center: Vec2, viewport: Vec2, bounds: Vec2) -> Camera {
+        let mut camera = Camera { center, viewport, bounds };
+        camera.center = camera.clamp(center);
+        camera
+    }
+
+    /// Moves the `smoothing` fraction of the way to `target`; called once per tick.
+    pub fn follow(&mut self, target: Vec2, smoothing: f32) {
+        self.center = self.clamp(self.center + (target - self.center) * smoothing);
+    }
+
+    /// Keeps the view inside the world, or centered on it when the world is
+    /// smaller than the window.
+    fn clamp(&self, center: Vec2) -> Vec2 {
+        let half = self.viewport / 2f32;
+        let axis = |center: f32, half: f32, bound: f32| {
+            if bound <= half * 2f32 { bound / 2f32 } else { center.clamp(half, bound - half) }
+        };
+        Vec2::new(axis(center.x, half.x, self.bounds.x), axis(center.y, half.y, self.bounds.y))
+    }
+
+    /// World position of the top left corner of the window.
+    pub fn top_left(&self) -> Vec2 {
+        self.center - self.viewport / 2f32
+    }
+
+    pub fn to_world(&self, screen: Vec2) -> Vec2 {
+        screen + self.top_left()
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use crate::world::GameMode;
+
+/// Command line options understood by the game.
+#[derive(Debug)]
+pub struct Options {
+    /// Run the simulation without a window and print a summary.
+    pub headless: bool,
+    /// How many ticks a headless run lasts; defaults to the replay length or one minute.
+    pub ticks: Option<u64>,
+    /// Seed for the gameplay RNG; a random one is picked when absent.
+    pub seed: Option<u64>,
+    /// Game mode of a headless run; windowed sessions pick it in the menu.
+    pub mode: GameMode,
+    /// Replay file whose inputs drive the simulation instead of the keyboard and mouse.
+    pub replay: Option<PathBuf>,
+    /// Config file with the gameplay tuning values; `config.toml` is used when absent.
+    pub config: Option<PathBuf>,
+    /// Directory with the images and sounds, searched for when absent.
+    pub resources: Option<PathBuf>,
+    /// Where the inputs of a windowed session are recorded to.
+    pub record: PathBuf,
+    /// Work the lighting out on the CPU, for software renderers like llvmpipe.
+    pub software_lighting: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            headless: false,
+            ticks: None,
+            seed: None,
+            mode: GameMode::Classic,
+            replay: None,
+            config: None,
+            resources: None,
+            record: PathBuf::from("last.replay"),
+            software_lighting: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_args() -> Result<Options, String> {
+        Options::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {
+                    options.headless = true;
+                    if let Some(ticks) = args.next_if(|next| !next.starts_with("--")) {
+                        options.ticks = Some(ticks.parse().map_err(|_| format!("--headless expects a number of ticks, got `{ticks}`"))?);
+                    }
+                }
+                "--seed" => {
+                    let seed = value(&mut args, "--seed")?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("--seed expects an unsigned number, got `{seed}`"))?);
+                }
+                "--mode" => {
+                    let mode = value(&mut args, "--mode")?;
+                    options.mode = GameMode::from_name(&mode).ok_or_else(|| format!("--mode expects `classic` or `hardcore`, got `{mode}`"))?;
+                }
+                "--replay" => options.replay = Some(PathBuf::from(value(&mut args, "--replay")?)),
+                "--config" => options.config = Some(PathBuf::from(value(&mut args, "--config")?)),
+                "--resources" => options.resources = Some(PathBuf::from(value(&mut args, "--resources")?)),
+                "--record" => options.record = PathBuf::from(value(&mut args, "--record")?),
+                "--software-lighting" => options.software_lighting = true,
+                _ => return Err(format!("unknown argument `{arg}`\n{}", usage())),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{flag} expects a value"))
+}
+
+fn usage() -> &'static str {
+    "usage: rect_practice [--headless [ticks]] [--seed <n>] [--mode <classic|hardcore>] [--replay <file>] [--record <file>] [--config <file>] [--resources <dir>] [--software-lighting]"
+}
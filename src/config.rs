@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{PI, PX_MOVEMENT, VISION_RANGE, VISION_ANGLE, AMBIENT_RADIUS, HEARING_DISTANCE, MAGNET_RADIUS, MAGNET_SPEED, PARTICLE_HEALTH, PARTICLE_ANGLE,
+    NEIGHBOUR_RADIUS, SEPARATION_WEIGHT, ALIGNMENT_WEIGHT, ARRIVAL_RADIUS, STEERING_FORCE, CAMERA_SMOOTHING, ADAPTIVE_DIFFICULTY, INTENSITY_LOW, INTENSITY_HIGH,
+    SPAWN_INTERVAL_SCALE};
+use crate::data::GameData;
+use crate::weapons::{Weapon, MAX_FIRE_INTERVAL, MAX_RELOAD_TIME};
+use crate::enemies::{EnemyKind, MAX_SPEED};
+
+/// Where the game looks for its tuning values when `--config` is not given.
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Gameplay tuning values. Anything left out of the config file keeps the
+/// default from `settings.rs`. Weapons, enemy kinds, pickups and waves have
+/// their own files, see weapons.rs, enemies.rs, pickups.rs and waves.rs; the
+/// values of theirs that get balanced the most can be overridden here so they
+/// can be tuned while the game runs too.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub px_movement: f32,
+    /// Length of the cone the player sees in, towards where they aim.
+    pub vision_range: f32,
+    /// Width of that cone.
+    pub vision_angle: f32,
+    /// Distance the player sees all around them.
+    pub ambient_radius: f32,
+    /// Footsteps of enemies further away than this can't be heard.
+    pub hearing_distance: f32,
+    /// Pickups closer than this drift towards the player.
+    pub magnet_radius: f32,
+    /// Pixels per tick they drift at.
+    pub magnet_speed: f32,
+    pub particle_health: u16,
+    pub particle_angle: f32,
+    /// Enemies closer than this steer around each other, see steering.rs.
+    pub neighbour_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    /// Enemies slow down within this distance of where they are going.
+    pub arrival_radius: f32,
+    /// Largest change of an enemy's velocity in one tick.
+    pub steering_force: f32,
+    /// Share of the way to the player the camera moves each tick.
+    pub camera_smoothing: f32,
+    /// Lets the game adjust the waves to how the player is doing, see difficulty.rs.
+    pub adaptive_difficulty: bool,
+    /// Band the adaptive difficulty keeps the intensity in, from 0 to 1.
+    pub intensity_low: f32,
+    pub intensity_high: f32,
+    /// Multiplies the ticks between two spawns of every wave in waves.toml.
+    pub spawn_interval_scale: f32,
+    /// Overrides for weapons.toml, by weapon name.
+    pub weapons: BTreeMap<String, WeaponTuning>,
+    /// Overrides for enemies.toml, by enemy name.
+    pub enemies: BTreeMap<String, EnemyTuning>,
+}
+
+/// What of a weapon config.toml can override.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeaponTuning {
+    pub fire_interval: Option<u16>,
+    pub reload_time: Option<u16>,
+}
+
+/// What of an enemy kind config.toml can override.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnemyTuning {
+    pub speed: Option<f32>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            px_movement: PX_MOVEMENT,
+            vision_range: VISION_RANGE,
+            vision_angle: VISION_ANGLE,
+            ambient_radius: AMBIENT_RADIUS,
+            hearing_distance: HEARING_DISTANCE,
+            magnet_radius: MAGNET_RADIUS,
+            magnet_speed: MAGNET_SPEED,
+            particle_health: PARTICLE_HEALTH,
+            particle_angle: PARTICLE_ANGLE,
+            neighbour_radius: NEIGHBOUR_RADIUS,
+            separation_weight: SEPARATION_WEIGHT,
+            alignment_weight: ALIGNMENT_WEIGHT,
+            arrival_radius: ARRIVAL_RADIUS,
+            steering_force: STEERING_FORCE,
+            camera_smoothing: CAMERA_SMOOTHING,
+            adaptive_difficulty: ADAPTIVE_DIFFICULTY,
+            intensity_low: INTENSITY_LOW,
+            intensity_high: INTENSITY_HIGH,
+            spawn_interval_scale: SPAWN_INTERVAL_SCALE,
+            weapons: BTreeMap::new(),
+            enemies: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file at `path`. A missing file is only an error when
+    /// `required` is set, i.e. when the user pointed at it explicitly.
+    pub fn load(path: &Path, required: bool) -> Result<Config, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Config::parse(&text).map_err(|err| format!("{}: {err}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => Ok(Config::default()),
+            Err(err) => Err(format!("could not read config {}: {err}", path.display())),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        check("px_movement", self.px_movement, 0f32, 50f32)?;
+        check("vision_range", self.vision_range, 0f32, 10000f32)?;
+        check("vision_angle", self.vision_angle, 0f32, 2f32 * PI)?;
+        check("ambient_radius", self.ambient_radius, 0f32, 10000f32)?;
+        check("hearing_distance", self.hearing_distance, 0f32, 10000f32)?;
+        check("magnet_radius", self.magnet_radius, 0f32, 10000f32)?;
+        check("magnet_speed", self.magnet_speed, 0f32, 100f32)?;
+        check("particle_health", self.particle_health, 1, 600)?;
+        check("particle_angle", self.particle_angle, 0f32, 2f32 * PI)?;
+        check("neighbour_radius", self.neighbour_radius, 0f32, 1000f32)?;
+        check("separation_weight", self.separation_weight, 0f32, 100f32)?;
+        check("alignment_weight", self.alignment_weight, 0f32, 100f32)?;
+        check("arrival_radius", self.arrival_radius, 0f32, 1000f32)?;
+        check("steering_force", self.steering_force, 0.01, 100f32)?;
+        check("camera_smoothing", self.camera_smoothing, 0.01, 1f32)?;
+        check("intensity_low", self.intensity_low, 0f32, 1f32)?;
+        check("intensity_high", self.intensity_high, self.intensity_low, 1f32)?;
+        check("spawn_interval_scale", self.spawn_interval_scale, 0.1, 10f32)?;
+        for (name, tuning) in &self.weapons {
+            if let Some(fire_interval) = tuning.fire_interval {
+                check(&format!("weapons.{name}.fire_interval"), fire_interval, 1, MAX_FIRE_INTERVAL)?;
+            }
+            if let Some(reload_time) = tuning.reload_time {
+                check(&format!("weapons.{name}.reload_time"), reload_time, 1, MAX_RELOAD_TIME)?;
+            }
+        }
+        for (name, tuning) in &self.enemies {
+            if let Some(speed) = tuning.speed {
+                check(&format!("enemies.{name}.speed"), speed, 0f32, MAX_SPEED)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails when an override names something the data files don't define,
+    /// which `validate` can't tell without them.
+    pub fn check_names(&self, data: &GameData) -> Result<(), String> {
+        for name in self.weapons.keys() {
+            if !data.weapons.iter().any(|weapon| &weapon.name == name) {
+                return Err(format!("`weapons.{name}`: there is no weapon named `{name}`"));
+            }
+        }
+        for name in self.enemies.keys() {
+            if data.enemies.id(name).is_none() {
+                return Err(format!("`enemies.{name}`: there is no enemy named `{name}`"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ticks between two shots of `weapon`.
+    pub fn fire_interval(&self, weapon: &Weapon) -> u16 {
+        self.weapons.get(&weapon.name).and_then(|tuning| tuning.fire_interval).unwrap_or(weapon.fire_interval)
+    }
+
+    /// Ticks it takes to reload `weapon`.
+    pub fn reload_time(&self, weapon: &Weapon) -> u16 {
+        self.weapons.get(&weapon.name).and_then(|tuning| tuning.reload_time).unwrap_or(weapon.reload_time)
+    }
+
+    /// Pixels per tick enemies of `kind` walk at before wave scaling.
+    pub fn enemy_speed(&self, kind: &EnemyKind) -> f32 {
+        self.enemies.get(&kind.name).and_then(|tuning| tuning.speed).unwrap_or(kind.speed)
+    }
+}
+
+/// Fails unless `value` lies between `min` and `max`, naming the offending setting.
+pub fn check<T: PartialOrd + std::fmt::Display>(name: &str, value: T, min: T, max: T) -> Result<(), String> {
+    // written so that NaN fails the check as well
+    if value >= min && value <= max {
+        Ok(())
+    }
+    else {
+        Err(format!("`{name}` must be between {min} and {max}, got {value}"))
+    }
+}
+
+/// Notices when the config file changes on disk so it can be re-applied while
+/// the game is running.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> ConfigWatcher {
+        let modified = modified(&path);
+        ConfigWatcher { path, modified }
+    }
+
+    /// Returns the freshly parsed config when the file changed since the last
+    /// call, or the reason it could not be used with `data`.
+    pub fn poll(&mut self, data: &GameData) -> Option<Result<Config, String>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load(&self.path, true).and_then(|config| {
+            config.check_names(data).map_err(|err| format!("{}: {err}", self.path.display()))?;
+            Ok(config)
+        }))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
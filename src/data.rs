@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use crate::assets::{Manifest, ImageHandle};
+use crate::animation::{Clips, ClipId};
+use crate::weapons::Weapons;
+use crate::enemies::EnemyKinds;
+use crate::waves::Waves;
+use crate::level::Level;
+use crate::pickups::PickupKinds;
+
+/// Images the simulation hands out to entities that are not animated.
+pub struct Sprites{
+    pub bullet: ImageHandle,
+    pub blood_particle: ImageHandle,
+}
+
+/// Clips the simulation switches between on its own.
+pub struct CoreClips{
+    pub player_idle: ClipId,
+    pub player_walk: ClipId,
+}
+
+/// Everything the simulation reads from the resources directory. Loaded once
+/// and shared by every round.
+pub struct GameData{
+    pub sprites: Sprites,
+    pub clips: Clips,
+    pub core_clips: CoreClips,
+    pub weapons: Weapons,
+    pub pickups: PickupKinds,
+    pub enemies: EnemyKinds,
+    pub waves: Waves,
+    pub level: Level,
+}
+
+impl GameData{
+    /// Reads the data files and registers every asset they mention in `manifest`.
+    pub fn load(resources_dir: &Path, manifest: &mut Manifest) -> Result<GameData, String> {
+        let sprites = Sprites {
+            bullet: manifest.image("/bullet.png"),
+            blood_particle: manifest.image("/blood_particle.png"),
+        };
+        let clips = Clips::load(resources_dir, manifest)?;
+        let core_clips = CoreClips {
+            player_idle: clips.require("player_idle")?,
+            player_walk: clips.require("player_walk")?,
+        };
+        let weapons = Weapons::load(resources_dir, manifest)?;
+        let pickups = PickupKinds::load(resources_dir, manifest)?;
+        let enemies = EnemyKinds::load(resources_dir, &clips, &pickups)?;
+        let level = Level::load(resources_dir, manifest, &pickups)?;
+        let waves = Waves::load(resources_dir, &enemies, &level)?;
+        Ok(GameData { sprites, clips, core_clips, weapons, pickups, enemies, waves, level })
+    }
+}
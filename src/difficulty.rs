@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+/// Samples (one a second) the director looks back on.
+const WINDOW: usize = 10;
+/// Kills per second at which the player counts as keeping up.
+const COMFORTABLE_KILL_RATE: f32 = 0.5;
+/// Change of the level per sample while the intensity is outside the band.
+const LEVEL_STEP: f32 = 0.05;
+const MIN_LEVEL: f32 = 0.5;
+const MAX_LEVEL: f32 = 2f32;
+
+/// How the round is going, as running totals taken once a second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Performance {
+    /// Player health as a fraction of what the round started with.
+    pub health: f32,
+    pub kills: u32,
+    /// Bullets fired.
+    pub shots: u32,
+    /// Bullets that hit an enemy.
+    pub hits: u32,
+}
+
+/// Watches how the player is doing and makes the waves easier or harder to
+/// keep the intensity between two bounds.
+#[derive(Debug, Clone)]
+pub struct AdaptiveDifficulty {
+    samples: VecDeque<Performance>,
+    /// How hard the player is pressed, from 0 (bored) to 1 (overwhelmed).
+    pub intensity: f32,
+    /// 1 plays the waves as defined; above makes them harder, below easier.
+    pub level: f32,
+}
+
+impl AdaptiveDifficulty {
+    pub fn new() -> AdaptiveDifficulty {
+        AdaptiveDifficulty { samples: VecDeque::new(), intensity: 0f32, level: 1f32 }
+    }
+
+    /// Takes the sample of the past second and nudges the level towards the
+    /// band from `low` to `high`.
+    pub fn sample(&mut self, now: Performance, low: f32, high: f32) {
+        self.samples.push_back(now);
+        //one more than the window, so there are WINDOW seconds between the ends
+        if self.samples.len() > WINDOW + 1 {
+            self.samples.pop_front();
+        }
+        self.intensity = self.measure();
+        if self.intensity < low {
+            self.level = (self.level + LEVEL_STEP).min(MAX_LEVEL);
+        }
+        else if self.intensity > high {
+            self.level = (self.level - LEVEL_STEP).max(MIN_LEVEL);
+        }
+    }
+
+    fn measure(&self) -> f32 {
+        let (first, last) = (self.samples.front().unwrap(), self.samples.back().unwrap());
+        let secs = (self.samples.len() - 1) as f32;
+        //without history yet, only the health says anything
+        let kill_rate = if secs > 0f32 { (last.kills - first.kills) as f32 / secs } else { COMFORTABLE_KILL_RATE };
+        let behind = 1f32 - (kill_rate / COMFORTABLE_KILL_RATE).min(1f32);
+        let shots = last.shots - first.shots;
+        let accuracy = if shots > 0 { ((last.hits - first.hits) as f32 / shots as f32).min(1f32) } else { 0.5 };
+        let hurt = 1f32 - last.health.clamp(0f32, 1f32);
+        0.5 * hurt + 0.3 * behind + 0.2 * (1f32 - accuracy)
+    }
+
+    /// Multiplies the ticks between two spawns.
+    pub fn interval_scale(&self) -> f32 {
+        1f32 / self.level
+    }
+
+    /// Multiplies the speed of every enemy.
+    pub fn speed_scale(&self) -> f32 {
+        self.level.sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOW: f32 = 0.35;
+    const HIGH: f32 = 0.65;
+
+    /// Feeds `secs` samples of a player at `health` that kills `kills_per_sec`
+    /// and fires `shots_per_sec`, `hits_per_sec` of which hit.
+    fn play(director: &mut AdaptiveDifficulty, secs: u32, health: f32, kills_per_sec: u32, shots_per_sec: u32, hits_per_sec: u32) {
+        let mut now = director.samples.back().copied()
+            .unwrap_or(Performance { health, kills: 0, shots: 0, hits: 0 });
+        for _ in 0..secs {
+            now.health = health;
+            now.kills += kills_per_sec;
+            now.shots += shots_per_sec;
+            now.hits += hits_per_sec;
+            director.sample(now, LOW, HIGH);
+        }
+    }
+
+    #[test]
+    fn starts_at_the_defined_difficulty() {
+        let director = AdaptiveDifficulty::new();
+        assert_eq!(director.level, 1f32);
+        assert_eq!(director.interval_scale(), 1f32);
+        assert_eq!(director.speed_scale(), 1f32);
+    }
+
+    #[test]
+    fn a_dominating_player_gets_harder_waves() {
+        let mut director = AdaptiveDifficulty::new();
+        play(&mut director, 5, 1f32, 2, 4, 4);
+        assert!(director.intensity < LOW, "intensity {}", director.intensity);
+        assert!(director.level > 1f32);
+        assert!(director.interval_scale() < 1f32);
+        assert!(director.speed_scale() > 1f32);
+    }
+
+    #[test]
+    fn a_struggling_player_gets_easier_waves() {
+        let mut director = AdaptiveDifficulty::new();
+        play(&mut director, 5, 0.2, 0, 4, 0);
+        assert!(director.intensity > HIGH, "intensity {}", director.intensity);
+        assert!(director.level < 1f32);
+        assert!(director.interval_scale() > 1f32);
+        assert!(director.speed_scale() < 1f32);
+    }
+
+    #[test]
+    fn intensity_inside_the_band_keeps_the_level() {
+        let mut director = AdaptiveDifficulty::new();
+        //half health, keeping up with kills and hitting a quarter of the shots
+        play(&mut director, 30, 0.5, 1, 4, 1);
+        assert!(director.intensity >= LOW && director.intensity <= HIGH, "intensity {}", director.intensity);
+        assert_eq!(director.level, 1f32);
+    }
+
+    #[test]
+    fn level_stays_within_its_limits() {
+        let mut director = AdaptiveDifficulty::new();
+        play(&mut director, 500, 1f32, 2, 4, 4);
+        assert_eq!(director.level, MAX_LEVEL);
+        play(&mut director, 500, 0f32, 0, 4, 0);
+        assert_eq!(director.level, MIN_LEVEL);
+    }
+
+    #[test]
+    fn only_the_last_seconds_count() {
+        let mut director = AdaptiveDifficulty::new();
+        //a killing spree long ago doesn't help a player that stopped killing
+        play(&mut director, 20, 0.5, 3, 3, 3);
+        play(&mut director, WINDOW as u32, 0.5, 0, 0, 0);
+        assert!(director.intensity > 0.5, "intensity {}", director.intensity);
+    }
+
+    #[test]
+    fn not_shooting_counts_as_average_accuracy() {
+        let mut idle = AdaptiveDifficulty::new();
+        play(&mut idle, 5, 1f32, 1, 0, 0);
+        let mut average = AdaptiveDifficulty::new();
+        play(&mut average, 5, 1f32, 1, 2, 1);
+        assert_eq!(idle.intensity, average.intensity);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::animation::{Clips, ClipId};
+use crate::config::check;
+use crate::pickups::{PickupKinds, PickupKindId};
+
+/// File in the resources directory that defines every kind of enemy.
+pub const ENEMIES_FILE: &str = "enemies.toml";
+/// Fastest an enemy may walk, in pixels per tick, even after wave scaling.
+pub const MAX_SPEED: f32 = 50f32;
+/// Most health an enemy may have, even after wave scaling.
+pub const MAX_HEALTH: u16 = 10000;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnemiesFile {
+    enemy: Vec<EnemyDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnemyDef {
+    name: String,
+    clip: String,
+    #[serde(default = "default_scale")]
+    scale: f32,
+    #[serde(default = "default_tint")]
+    tint: [f32; 3],
+    speed: f32,
+    health: u16,
+    damage: u16,
+    reward: u16,
+    #[serde(default)]
+    drop_chance: f32,
+    #[serde(default)]
+    drops: BTreeMap<String, u32>,
+    spit: Option<Spit>,
+    explode: Option<Explode>,
+    split: Option<SplitDef>,
+}
+
+fn default_scale() -> f32 {
+    1f32
+}
+
+fn default_tint() -> [f32; 3] {
+    [1f32, 1f32, 1f32]
+}
+
+/// Keeps its distance and shoots at the player.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spit {
+    /// Distance from which it starts shooting, in pixels.
+    pub range: f32,
+    /// Ticks between two shots.
+    pub interval: u16,
+    pub speed: f32,
+    pub damage: u16,
+}
+
+/// Blows up when it dies or reaches the player, hurting everything around.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Explode {
+    pub radius: f32,
+    pub damage: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SplitDef {
+    into: String,
+    count: u16,
+}
+
+/// What an enemy does besides walking at the player.
+#[derive(Debug, Clone, Copy)]
+pub enum Behaviour {
+    Chase,
+    Spit(Spit),
+    Explode(Explode),
+    /// Falls apart into `count` enemies of kind `into` when killed.
+    Split { into: EnemyKindId, count: u16 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnemyKindId(u16);
+
+#[derive(Debug)]
+pub struct EnemyKind {
+    pub name: String,
+    pub clip: ClipId,
+    /// Size relative to the sprite, for drawing and collisions.
+    pub scale: f32,
+    /// Color the sprite is multiplied with.
+    pub tint: [f32; 3],
+    /// Pixels per tick.
+    pub speed: f32,
+    pub health: u16,
+    /// Health the player loses when it reaches them.
+    pub damage: u16,
+    /// Dollars for killing it.
+    pub reward: u16,
+    /// Chance from 0 to 1 that it leaves a pickup behind when the player kills it.
+    pub drop_chance: f32,
+    /// Kinds of pickup it may leave behind, each with its weight.
+    pub drops: Vec<(PickupKindId, u32)>,
+    pub behaviour: Behaviour,
+}
+
+/// Every kind of enemy the game knows, by name.
+#[derive(Debug)]
+pub struct EnemyKinds {
+    kinds: Vec<EnemyKind>,
+}
+
+impl EnemyKinds {
+    pub fn load(resources_dir: &Path, clips: &Clips, pickups: &PickupKinds) -> Result<EnemyKinds, String> {
+        let path = resources_dir.join(ENEMIES_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        EnemyKinds::parse(&text, clips, pickups).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str, clips: &Clips, pickups: &PickupKinds) -> Result<EnemyKinds, String> {
+        let file: EnemiesFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        let names: Vec<&str> = file.enemy.iter().map(|def| def.name.as_str()).collect();
+        let mut kinds: Vec<EnemyKind> = Vec::new();
+        for def in &file.enemy {
+            if kinds.iter().any(|kind| kind.name == def.name) {
+                return Err(format!("enemy `{}` is defined twice", def.name));
+            }
+            let in_enemy = |err: String| format!("enemy `{}`: {err}", def.name);
+            check("scale", def.scale, 0.1, 10f32).map_err(in_enemy)?;
+            check("speed", def.speed, 0f32, MAX_SPEED).map_err(in_enemy)?;
+            check("health", def.health, 1, MAX_HEALTH).map_err(in_enemy)?;
+            let clip = clips.id(&def.clip).ok_or_else(|| in_enemy(format!("unknown clip `{}`", def.clip)))?;
+            check("drop_chance", def.drop_chance, 0f32, 1f32).map_err(in_enemy)?;
+            let mut drops = Vec::new();
+            for (name, weight) in &def.drops {
+                let pickup = pickups.id(name).ok_or_else(|| in_enemy(format!("drops unknown pickup `{name}`")))?;
+                check(&format!("drops.{name}"), *weight, 1, 10000).map_err(in_enemy)?;
+                drops.push((pickup, *weight));
+            }
+            if def.drop_chance > 0f32 && drops.is_empty() {
+                return Err(in_enemy("has a `drop_chance` but no `drops`".to_string()));
+            }
+
+            let behaviour = match (def.spit, def.explode, &def.split) {
+                (None, None, None) => Behaviour::Chase,
+                (Some(spit), None, None) => {
+                    check("spit.range", spit.range, 1f32, 10000f32).map_err(in_enemy)?;
+                    check("spit.interval", spit.interval, 1, 6000).map_err(in_enemy)?;
+                    check("spit.speed", spit.speed, 0.1, 200f32).map_err(in_enemy)?;
+                    Behaviour::Spit(spit)
+                }
+                (None, Some(explode), None) => {
+                    check("explode.radius", explode.radius, 1f32, 10000f32).map_err(in_enemy)?;
+                    Behaviour::Explode(explode)
+                }
+                (None, None, Some(split)) => {
+                    let into = names.iter().position(|name| *name == split.into)
+                        .ok_or_else(|| in_enemy(format!("splits into unknown enemy `{}`", split.into)))?;
+                    if split.into == def.name {
+                        return Err(in_enemy("can't split into itself".to_string()));
+                    }
+                    check("split.count", split.count, 1, 20).map_err(in_enemy)?;
+                    Behaviour::Split { into: EnemyKindId(into as u16), count: split.count }
+                }
+                _ => return Err(in_enemy("has more than one of `spit`, `explode` and `split`".to_string())),
+            };
+
+            kinds.push(EnemyKind {
+                name: def.name.clone(),
+                clip,
+                scale: def.scale,
+                tint: def.tint,
+                speed: def.speed,
+                health: def.health,
+                damage: def.damage,
+                reward: def.reward,
+                drop_chance: def.drop_chance,
+                drops,
+                behaviour,
+            });
+        }
+        Ok(EnemyKinds { kinds })
+    }
+
+    pub fn get(&self, id: EnemyKindId) -> &EnemyKind {
+        &self.kinds[id.0 as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<EnemyKindId> {
+        self.kinds.iter().position(|kind| kind.name == name).map(|index| EnemyKindId(index as u16))
+    }
+
+    /// Every kind with its id, in the order of the file.
+    pub fn iter(&self) -> impl Iterator<Item = (EnemyKindId, &EnemyKind)> {
+        self.kinds.iter().enumerate().map(|(index, kind)| (EnemyKindId(index as u16), kind))
+    }
+}
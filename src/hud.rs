@@ -0,0 +1,247 @@
+use ggez::{
+    event::MouseButton,
+    graphics::{self, Color},
+    glam::Vec2,
+    input::keyboard::KeyCode,
+    Context,
+    GameResult,
+};
+
+use enum_map::EnumMap;
+
+use crate::settings::TICKS_PER_SECOND;
+use crate::ui::{Ui, WidgetId, Anchor, Direction};
+use crate::world::{World, State};
+use crate::waves::Phase;
+use crate::input::SLOT_KEYS;
+use crate::pickups::PowerUp;
+
+/// A short message shown on the HUD for a few seconds.
+struct Toast {
+    ticks_left: u32,
+}
+
+struct GunEntry {
+    slot: usize,
+    status: WidgetId,
+    button: WidgetId,
+}
+
+/// Everything drawn over the world during a round: the stats in the corner,
+/// wave banners, the gun menu while the shop is open, the death screen and toasts.
+pub struct Hud {
+    ui: Ui,
+    fps: WidgetId,
+    enemies: WidgetId,
+    wave: WidgetId,
+    intensity: WidgetId,
+    intensity_bar: WidgetId,
+    hp: WidgetId,
+    hp_bar: WidgetId,
+    dollars: WidgetId,
+    ammo: WidgetId,
+    reload_bar: WidgetId,
+    reload_hint: WidgetId,
+    /// Time left of every power-up, shown while it is active.
+    power_ups: EnumMap<PowerUp, WidgetId>,
+    banner_label: WidgetId,
+    banner: Option<Toast>,
+    next_wave: WidgetId,
+    toast_label: WidgetId,
+    toast: Option<Toast>,
+    gun_menu: WidgetId,
+    guns: Vec<GunEntry>,
+    death: WidgetId,
+    survived: WidgetId,
+    kills: WidgetId,
+    waves_cleared: WidgetId,
+    dollars_earned: WidgetId,
+    pickups: WidgetId,
+}
+
+impl Hud {
+    pub fn new(world: &World) -> Hud {
+        let mut ui = Ui::new();
+
+        let stats = ui.root(Anchor::TopLeft, Direction::Column, None);
+        let fps = ui.label(stats, "", 16f32, Color::YELLOW);
+        let enemies = ui.label(stats, "", 16f32, Color::YELLOW);
+        let wave = ui.label(stats, "", 16f32, Color::YELLOW);
+        let intensity = ui.label(stats, "", 16f32, Color::YELLOW);
+        let intensity_bar = ui.progress_bar(stats, Vec2::new(150f32, 8f32), Color::new(1f32, 0.5, 0f32, 1f32));
+        let hp = ui.label(stats, "", 16f32, Color::YELLOW);
+        let hp_bar = ui.progress_bar(stats, Vec2::new(150f32, 8f32), Color::RED);
+        let dollars = ui.label(stats, "", 16f32, Color::YELLOW);
+        let ammo = ui.label(stats, "", 16f32, Color::YELLOW);
+        let reload_bar = ui.progress_bar(stats, Vec2::new(150f32, 8f32), Color::WHITE);
+        let reload_hint = ui.label(stats, "press R to reload", 16f32, Color::RED);
+        let power_ups = EnumMap::from_fn(|_| ui.label(stats, "", 16f32, Color::CYAN));
+
+        let toast_root = ui.root(Anchor::TopCenter, Direction::Column, None);
+        let banner_label = ui.label(toast_root, "", 48f32, Color::YELLOW);
+        let next_wave = ui.label(toast_root, "", 24f32, Color::WHITE);
+        let toast_label = ui.label(toast_root, "", 16f32, Color::WHITE);
+
+        let gun_menu = ui.root(Anchor::BottomBar, Direction::Row, Some(Color::new(0f32, 0f32, 0f32, 0.5)));
+        let mut guns = Vec::new();
+        for (slot, weapon) in world.data.weapons.iter().enumerate() {
+            let panel = ui.panel(gun_menu, Direction::Column, Some(Color::new(0f32, 0.1, 0.07, 0.8)));
+            ui.label(panel, weapon.name.as_str(), 20f32, Color::YELLOW);
+            let status = ui.label(panel, "", 16f32, Color::YELLOW);
+            let button = ui.button(panel, "", 16f32);
+            guns.push(GunEntry { slot, status, button });
+        }
+
+        let death = ui.root(Anchor::Center, Direction::Column, None);
+        ui.label(death, "YOU DIED", 64f32, Color::YELLOW);
+        let survived = ui.label(death, "", 32f32, Color::YELLOW);
+        let kills = ui.label(death, "", 32f32, Color::YELLOW);
+        let waves_cleared = ui.label(death, "", 32f32, Color::YELLOW);
+        let dollars_earned = ui.label(death, "", 32f32, Color::YELLOW);
+        let pickups = ui.label(death, "", 32f32, Color::YELLOW);
+        ui.label(death, "press R to restart or Escape for the menu", 24f32, Color::YELLOW);
+
+        Hud {
+            ui,
+            fps,
+            enemies,
+            wave,
+            intensity,
+            intensity_bar,
+            hp,
+            hp_bar,
+            dollars,
+            ammo,
+            reload_bar,
+            reload_hint,
+            power_ups,
+            banner_label,
+            banner: None,
+            next_wave,
+            toast_label,
+            toast: None,
+            gun_menu,
+            guns,
+            death,
+            survived,
+            kills,
+            waves_cleared,
+            dollars_earned,
+            pickups,
+        }
+    }
+
+    pub fn show_toast(&mut self, text: String, color: Color) {
+        self.ui.set_text(self.toast_label, text);
+        self.ui.set_color(self.toast_label, color);
+        self.toast = Some(Toast { ticks_left: 3 * TICKS_PER_SECOND });
+    }
+
+    /// Big text in the middle of the top edge, e.g. "Wave 3".
+    pub fn show_banner(&mut self, text: String) {
+        self.ui.set_text(self.banner_label, text);
+        self.banner = Some(Toast { ticks_left: 2 * TICKS_PER_SECOND });
+    }
+
+    /// Counts down timed elements; called once per simulation tick.
+    pub fn tick(&mut self) {
+        for timed in [&mut self.toast, &mut self.banner] {
+            if let Some(toast) = timed {
+                toast.ticks_left -= 1;
+                if toast.ticks_left == 0 {
+                    *timed = None;
+                }
+            }
+        }
+    }
+
+    /// Copies the current state of the round into the widgets and draws them.
+    pub fn draw(&mut self, ctx: &Context, canvas: &mut graphics::Canvas, world: &World, show_fps: bool) -> GameResult {
+        let ui = &mut self.ui;
+        ui.set_visible(self.fps, show_fps);
+        ui.set_text(self.fps, (ctx.time.fps() as i16).to_string());
+        ui.set_text(self.enemies, format!("enemies: {}", world.enemies.len()));
+        ui.set_text(self.wave, format!("wave: {}", world.director.wave));
+        ui.set_visible(self.intensity, world.config.adaptive_difficulty);
+        ui.set_visible(self.intensity_bar, world.config.adaptive_difficulty);
+        let difficulty = &world.difficulty;
+        ui.set_text(self.intensity, format!("intensity: {:.0}% (difficulty x{:.2})", difficulty.intensity * 100f32, difficulty.level));
+        ui.set_progress(self.intensity_bar, difficulty.intensity);
+        ui.set_text(self.hp, format!("HP: {}", world.player.health));
+        ui.set_progress(self.hp_bar, world.player.health as f32 / world.max_health as f32);
+        ui.set_text(self.dollars, format!("dollars: {}", world.dollars));
+        let weapon = world.data.weapons.get(world.using_gun);
+        let ammo = &world.ammo[world.using_gun];
+        let reserve = match ammo.reserve {
+            Some(reserve) => reserve.to_string(),
+            None => "unlimited".to_string(),
+        };
+        ui.set_text(self.ammo, format!("ammo: {} / {} (reserve: {reserve})", ammo.magazine, weapon.magazine));
+        ui.set_visible(self.reload_bar, world.reload_left > 0);
+        ui.set_progress(self.reload_bar, 1f32 - world.reload_left as f32 / world.config.reload_time(weapon) as f32);
+        ui.set_visible(self.reload_hint, ammo.magazine == 0 && world.reload_left == 0 && ammo.reserve != Some(0));
+        for (power_up, label) in &self.power_ups {
+            let ticks_left = world.power_ups[power_up];
+            ui.set_visible(*label, ticks_left > 0);
+            ui.set_text(*label, format!("{}: {}s", power_up.label(), ticks_left as u32 / TICKS_PER_SECOND + 1));
+        }
+        ui.set_visible(self.toast_label, self.toast.is_some());
+        ui.set_visible(self.banner_label, self.banner.is_some());
+
+        let intermission = match world.director.phase {
+            Phase::Intermission { ticks_left } if world.shop_open() => Some(ticks_left),
+            _ => None,
+        };
+        ui.set_visible(self.next_wave, intermission.is_some() && world.state == State::Playing);
+        if let Some(ticks_left) = intermission {
+            let secs = ticks_left as u32 / TICKS_PER_SECOND + 1;
+            ui.set_text(self.next_wave, format!("wave {} in {secs}s, the gun shop is open", world.director.wave));
+        }
+
+        ui.set_visible(self.gun_menu, world.shop_open());
+        for entry in &self.guns {
+            let (status, action) = if entry.slot == world.using_gun {
+                ("currently using".to_string(), "currently using".to_string())
+            }
+            else if world.owned_guns[entry.slot] {
+                ("ready for use".to_string(), "switch to".to_string())
+            }
+            else {
+                ("not bought".to_string(), format!("buy for {} dollars", world.data.weapons.get(entry.slot).price))
+            };
+            ui.set_text(entry.status, status);
+            ui.set_text(entry.button, format!("{action} (key {})", entry.slot + 1));
+        }
+
+        ui.set_visible(self.death, world.state == State::GameOver);
+        let secs = world.stats.ticks_survived / TICKS_PER_SECOND as u64;
+        ui.set_text(self.survived, format!("survived: {}:{:02}", secs / 60, secs % 60));
+        ui.set_text(self.kills, format!("kills: {} ({} hits)", world.stats.kills, world.stats.hits));
+        ui.set_text(self.waves_cleared, format!("waves cleared: {}", world.stats.waves_cleared));
+        ui.set_text(self.dollars_earned, format!("dollars earned: {}", world.stats.dollars_earned));
+        ui.set_text(self.pickups, format!("pickups: {}", world.stats.pickups));
+
+        ui.draw(ctx, canvas)
+    }
+
+    pub fn mouse_motion(&mut self, pos: Vec2) {
+        self.ui.mouse_motion(pos);
+    }
+
+    /// The key a click on a gun button stands for, so it can go through the
+    /// regular (recorded) input path.
+    pub fn mouse_down(&mut self, button: MouseButton, pos: Vec2) -> Option<KeyCode> {
+        let clicked = self.ui.mouse_down(button, pos)?;
+        self.gun_key(clicked)
+    }
+
+    /// Same as `mouse_down`, for moving the focus with the arrow keys and pressing Enter.
+    pub fn key_down(&mut self, key: KeyCode) -> Option<KeyCode> {
+        let activated = self.ui.key_down(key)?;
+        self.gun_key(activated)
+    }
+
+    fn gun_key(&self, button: WidgetId) -> Option<KeyCode> {
+        self.guns.iter().find(|entry| entry.button == button).map(|entry| SLOT_KEYS[entry.slot])
+    }
+}
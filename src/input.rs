@@ -0,0 +1,99 @@
+use ggez::input::keyboard::KeyCode;
+
+use crate::world::Input;
+
+/// Keys the game reacts to, with the names used for them in replay files.
+const KEYS: &[(KeyCode, &str)] = &[
+    (KeyCode::W, "W"),
+    (KeyCode::A, "A"),
+    (KeyCode::S, "S"),
+    (KeyCode::D, "D"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::P, "P"),
+    (KeyCode::R, "R"),
+    (KeyCode::Key1, "Key1"),
+    (KeyCode::Key2, "Key2"),
+    (KeyCode::Key3, "Key3"),
+    (KeyCode::Key4, "Key4"),
+    (KeyCode::Key5, "Key5"),
+    (KeyCode::Key6, "Key6"),
+    (KeyCode::Key7, "Key7"),
+    (KeyCode::Key8, "Key8"),
+    (KeyCode::Key9, "Key9"),
+];
+
+/// Keys that buy or pick the weapon in the matching slot of the weapons file.
+pub const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+    KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEYS.iter().find(|(code, _)| *code == key).map(|(_, name)| *name)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEYS.iter().find(|(_, n)| *n == name).map(|(code, _)| *code)
+}
+
+/// A raw input event as delivered by the window, before it is folded into an `Input`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    KeyDown(KeyCode),
+    KeyUp(KeyCode),
+    MouseMotion(f32, f32),
+}
+
+impl InputEvent {
+    /// Whether the game reacts to this event at all; only those are worth recording.
+    pub fn is_relevant(&self) -> bool {
+        match self {
+            InputEvent::KeyDown(key) | InputEvent::KeyUp(key) => key_name(*key).is_some(),
+            InputEvent::MouseMotion(..) => true,
+        }
+    }
+}
+
+impl Input {
+    /// Folds a single event into the snapshot the next tick will see.
+    pub fn apply(&mut self, event: InputEvent) {
+        match event {
+            // if we press WAS or D, move accordingly
+            InputEvent::KeyDown(key) => match key {
+                KeyCode::W => self.up = true,
+                KeyCode::A => self.left = true,
+                KeyCode::S => self.down = true,
+                KeyCode::D => self.right = true,
+                KeyCode::Space => self.fire = true,
+                KeyCode::P => self.toggle_pause = true,
+                KeyCode::R => self.reload = true,
+                _ => {
+                    if let Some(slot) = SLOT_KEYS.iter().position(|slot_key| *slot_key == key) {
+                        self.select_gun = Some(slot);
+                    }
+                }
+            },
+            InputEvent::KeyUp(key) => match key {
+                KeyCode::W => self.up = false,
+                KeyCode::A => self.left = false,
+                KeyCode::S => self.down = false,
+                KeyCode::D => self.right = false,
+                KeyCode::Space => self.fire = false,
+                _ => (),
+            },
+            //make player "look" at mouse position.
+            InputEvent::MouseMotion(x, y) => {
+                self.aim.x = x;
+                self.aim.y = y;
+            }
+        }
+    }
+
+    /// Forgets the inputs that only count for the tick they were pressed in.
+    pub fn end_tick(&mut self) {
+        self.toggle_pause = false;
+        self.reload = false;
+        self.select_gun = None;
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use ggez::glam::Vec2;
+use ggez::graphics::Rect;
+
+use crate::assets::{ImageHandle, Manifest};
+use crate::physics::Aabb;
+use crate::pickups::{PickupKinds, PickupKindId};
+use crate::settings::PLAYER_SOLID_HALF;
+use crate::tilemap::{Layer, MapObject, TileMap};
+
+/// File in the resources directory that says which map is the level.
+pub const LEVEL_FILE: &str = "level.toml";
+
+/// Name of the tile layer whose tiles block movement instead of being drawn.
+const COLLISION_LAYER: &str = "collision";
+
+/// Thickness of the invisible walls around the arena that keep the player in.
+const BORDER: f32 = 100f32;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LevelFile {
+    map: String,
+}
+
+/// A tile of the map, ready to draw.
+#[derive(Debug)]
+pub struct Tile {
+    pub image: ImageHandle,
+    /// Part of the image the tile is cut from, as fractions of its size.
+    pub src: Rect,
+    pub bounds: Aabb,
+}
+
+/// An area of the map enemies walk in from. Waves refer to it by name.
+#[derive(Debug)]
+pub struct SpawnArea {
+    pub name: String,
+    pub bounds: Aabb,
+}
+
+/// The static geometry of the level.
+#[derive(Debug)]
+pub struct Level {
+    /// Width and height of the arena, in pixels.
+    pub size: Vec2,
+    /// Every tile to draw, bottom layer first.
+    pub tiles: Vec<Tile>,
+    /// Everything that blocks enemies, bullets and spit.
+    pub solids: Vec<Aabb>,
+    /// The solids plus the edges of the arena, which only block the player.
+    pub enclosed: Vec<Aabb>,
+    pub player_start: Vec2,
+    pub spawns: Vec<SpawnArea>,
+    /// Spots marked for pickups, with the kind of pickup each is stocked with.
+    pub pickups: Vec<(PickupKindId, Vec2)>,
+}
+
+impl Level {
+    pub fn load(resources_dir: &Path, manifest: &mut Manifest, kinds: &PickupKinds) -> Result<Level, String> {
+        let path = resources_dir.join(LEVEL_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        let file: LevelFile = toml::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?;
+        let map_path = resources_dir.join(&file.map);
+        let map = TileMap::load(&map_path)?;
+        Level::from_map(&map, &file.map, manifest, kinds).map_err(|err| format!("{}: {err}", map_path.display()))
+    }
+
+    /// Builds the level from `map`, found at `map_path` within the resources directory.
+    pub fn from_map(map: &TileMap, map_path: &str, manifest: &mut Manifest, kinds: &PickupKinds) -> Result<Level, String> {
+        let cell = Vec2::new(map.tile_width as f32, map.tile_height as f32);
+        let size = Vec2::new(map.width as f32, map.height as f32) * cell;
+        let images: Vec<ImageHandle> = map.tilesets.iter()
+            .map(|tileset| manifest.image(&resource_path(map_path, &tileset.image)))
+            .collect();
+
+        let mut tiles = Vec::new();
+        let mut solids = Vec::new();
+        let mut player_start = None;
+        let mut spawns: Vec<SpawnArea> = Vec::new();
+        let mut pickups = Vec::new();
+        for layer in &map.layers {
+            match layer {
+                Layer::Tiles { name, ids, .. } if name.eq_ignore_ascii_case(COLLISION_LAYER) => {
+                    let filled: Vec<bool> = ids.iter().map(|id| *id != 0).collect();
+                    solids.extend(merge_cells(&filled, map.width as usize, cell));
+                }
+                Layer::Tiles { visible: false, .. } => {}
+                Layer::Tiles { ids, .. } => {
+                    for (index, id) in ids.iter().enumerate().filter(|(_, id)| **id != 0) {
+                        let (set, tile) = map.tile(*id).unwrap();
+                        let tileset = &map.tilesets[set];
+                        let (x, y) = tileset.source(tile);
+                        let (width, height) = (tileset.tile_width as f32, tileset.tile_height as f32);
+                        let (image_width, image_height) = (tileset.image_width as f32, tileset.image_height as f32);
+                        //tiles taller than the cell stick out above it, like in the editor
+                        let column = (index % map.width as usize) as f32;
+                        let row = (index / map.width as usize) as f32;
+                        let pos = Vec2::new(column * cell.x, (row + 1f32) * cell.y - height);
+                        tiles.push(Tile {
+                            image: images[set],
+                            src: Rect::new(x as f32 / image_width, y as f32 / image_height, width / image_width, height / image_height),
+                            bounds: Aabb::new(pos, Vec2::new(width, height)),
+                        });
+                    }
+                }
+                Layer::Objects { name: layer_name, objects } => {
+                    for object in objects {
+                        let in_object = |err: String| format!("layer `{layer_name}`, object `{}`: {err}", object.name);
+                        let bounds = Aabb::new(Vec2::new(object.x, object.y), Vec2::new(object.width, object.height));
+                        let center = (bounds.min + bounds.max) / 2f32;
+                        match object.class.as_str() {
+                            "player_start" => {
+                                if player_start.replace(center).is_some() {
+                                    return Err(in_object("the map has more than one player_start".to_string()));
+                                }
+                            }
+                            "spawn" => {
+                                if object.name.is_empty() || spawns.iter().any(|spawn| spawn.name == object.name) {
+                                    return Err(in_object("every spawn needs a name of its own, waves refer to it".to_string()));
+                                }
+                                spawns.push(SpawnArea { name: object.name.clone(), bounds });
+                            }
+                            "pickup" => {
+                                let kind = kinds.id(&object.name)
+                                    .ok_or_else(|| in_object("a pickup must be named after a kind of pickup in pickups.toml".to_string()))?;
+                                pickups.push((kind, center));
+                            }
+                            "solid" => {
+                                if object.width <= 0f32 || object.height <= 0f32 {
+                                    return Err(in_object("a solid must be a rectangle or ellipse".to_string()));
+                                }
+                                solids.push(bounds);
+                            }
+                            other => return Err(in_object(format!("unknown class `{other}`, expected player_start, spawn, pickup or solid"))),
+                        }
+                        check_inside(object, size).map_err(in_object)?;
+                    }
+                }
+            }
+        }
+
+        let player_start = player_start.ok_or("the map has no object of class player_start")?;
+        if solids.iter().any(|solid| solid.overlaps(&Aabb::around(player_start, Vec2::splat(PLAYER_SOLID_HALF)))) {
+            return Err("the player_start is blocked by a solid".to_string());
+        }
+        if spawns.is_empty() {
+            return Err("the map has no object of class spawn".to_string());
+        }
+
+        let mut enclosed = solids.clone();
+        enclosed.extend([
+            Aabb::new(Vec2::new(-BORDER, -BORDER), Vec2::new(BORDER, size.y + 2f32 * BORDER)),
+            Aabb::new(Vec2::new(size.x, -BORDER), Vec2::new(BORDER, size.y + 2f32 * BORDER)),
+            Aabb::new(Vec2::new(0f32, -BORDER), Vec2::new(size.x, BORDER)),
+            Aabb::new(Vec2::new(0f32, size.y), Vec2::new(size.x, BORDER)),
+        ]);
+        Ok(Level { size, tiles, solids, enclosed, player_start, spawns, pickups })
+    }
+
+    /// Index of the spawn area called `name`.
+    pub fn spawn_id(&self, name: &str) -> Option<usize> {
+        self.spawns.iter().position(|spawn| spawn.name == name)
+    }
+}
+
+fn check_inside(object: &MapObject, size: Vec2) -> Result<(), String> {
+    if object.x < 0f32 || object.y < 0f32 || object.x + object.width > size.x || object.y + object.height > size.y {
+        return Err(format!("lies outside the {} by {} map", size.x, size.y));
+    }
+    Ok(())
+}
+
+/// Path ggez loads `image` from, given relative to the map at `map_path`.
+fn resource_path(map_path: &str, image: &str) -> String {
+    let mut parts: Vec<&str> = map_path.split(['/', '\\']).collect();
+    parts.pop();
+    for part in image.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
+
+/// Turns the filled cells of a `width` cells wide grid into boxes, merging runs
+/// of cells in a row and runs that line up in the rows below them.
+fn merge_cells(filled: &[bool], width: usize, cell: Vec2) -> Vec<Aabb> {
+    //(first column, past the last column, first row, rows)
+    let mut open: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let mut boxes = Vec::new();
+    for (row, cells) in filled.chunks(width).enumerate() {
+        let mut still_open = Vec::new();
+        let mut column = 0;
+        while column < width {
+            if !cells[column] {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while column < width && cells[column] {
+                column += 1;
+            }
+            match open.iter().position(|run| run.0 == start && run.1 == column) {
+                Some(index) => {
+                    let mut run = open.swap_remove(index);
+                    run.3 += 1;
+                    still_open.push(run);
+                }
+                None => still_open.push((start, column, row, 1)),
+            }
+        }
+        boxes.append(&mut open);
+        open = still_open;
+    }
+    boxes.append(&mut open);
+    boxes.into_iter()
+        .map(|(start, end, row, rows)| {
+            let pos = Vec2::new(start as f32 * cell.x, row as f32 * cell.y);
+            Aabb::new(pos, Vec2::new((end - start) as f32 * cell.x, rows as f32 * cell.y))
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use ggez::{
+    Context,
+    GameResult,
+    glam::Vec2,
+    graphics::{self, BlendComponent, BlendFactor, BlendMode, BlendOperation, Canvas, Color, DrawParam, Image, Mesh, MeshData, Rect, Vertex},
+};
+
+use crate::settings::{WIN_WIDTH, WIN_HEIGHT, PI, FOG_DARKNESS};
+use crate::vision::Sight;
+use crate::world::World;
+
+const MUZZLE_FLASH_RADIUS: f32 = 220f32;
+const MUZZLE_FLASH_COLOR: [f32; 3] = [1f32, 0.8, 0.45];
+const MUZZLE_FLASH_TICKS: u16 = 4;
+const EXPLOSION_COLOR: [f32; 3] = [1f32, 0.45, 0.1];
+const EXPLOSION_TICKS: u16 = 24;
+const PICKUP_LIGHT_RADIUS: f32 = 60f32;
+/// How strongly lights tint what they shine on, from 0 to 1.
+const GLOW: f32 = 0.35;
+
+/// Size of the cells the software path works the light out for, in pixels.
+const SOFTWARE_CELL: f32 = 24f32;
+
+/// Takes the alpha of what is drawn off the darkness of the mask, leaving its
+/// color alone.
+const CARVE: BlendMode = BlendMode {
+    color: BlendComponent { src_factor: BlendFactor::Zero, dst_factor: BlendFactor::One, operation: BlendOperation::Add },
+    alpha: BlendComponent { src_factor: BlendFactor::One, dst_factor: BlendFactor::One, operation: BlendOperation::ReverseSubtract },
+};
+
+/// A light that fades out over `lifetime` ticks.
+#[derive(Debug, Clone, Copy)]
+struct Light {
+    pos: Vec2,
+    radius: f32,
+    color: [f32; 3],
+    ticks_left: u16,
+    lifetime: u16,
+}
+
+impl Light {
+    fn strength(&self) -> f32 {
+        self.ticks_left as f32 / self.lifetime as f32
+    }
+}
+
+/// A light as it shines this frame: walls cast shadows over it like they do
+/// over the player's sight.
+struct Shining {
+    light: Light,
+    sight: Sight,
+    outline: Vec<Vec2>,
+}
+
+/// Darkness over everything the player can't see, lit up by the player's
+/// flashlight (their sight), by muzzle flashes and explosions and by the
+/// pickups lying around.
+///
+/// Normally the darkness is a mask the size of the window, drawn on the GPU
+/// with the lights carved out of it. The software path, for renderers like
+/// llvmpipe, works out how lit the corners of a coarse grid are on the CPU
+/// instead and draws the grid straight onto the frame; it needs no image to
+/// render to and no blend modes past the usual ones.
+pub struct Lighting {
+    software: bool,
+    mask: Option<Image>,
+    lights: Vec<Light>,
+    sight: Option<(Sight, Vec<Vec2>)>,
+    shining: Vec<Shining>,
+}
+
+impl Lighting {
+    pub fn new(software: bool) -> Lighting {
+        Lighting { software, mask: None, lights: Vec::new(), sight: None, shining: Vec::new() }
+    }
+
+    pub fn muzzle_flash(&mut self, pos: Vec2) {
+        self.add(pos, MUZZLE_FLASH_RADIUS, MUZZLE_FLASH_COLOR, MUZZLE_FLASH_TICKS);
+    }
+
+    /// Lights up twice the `radius` of the blast.
+    pub fn explosion(&mut self, pos: Vec2, radius: f32) {
+        self.add(pos, radius * 2f32, EXPLOSION_COLOR, EXPLOSION_TICKS);
+    }
+
+    fn add(&mut self, pos: Vec2, radius: f32, color: [f32; 3], lifetime: u16) {
+        self.lights.push(Light { pos, radius, color, ticks_left: lifetime, lifetime });
+    }
+
+    /// Fades the lights; called once per world tick.
+    pub fn tick(&mut self) {
+        for light in &mut self.lights {
+            light.ticks_left -= 1;
+        }
+        self.lights.retain(|light| light.ticks_left > 0);
+    }
+
+    /// Works out what is lit this frame for the world as seen from `view`, the
+    /// top left corner of the window in the world, and renders the mask. Has to
+    /// happen before the frame's canvas is made.
+    pub fn prepare(&mut self, ctx: &mut Context, world: &World, view: Vec2) -> GameResult {
+        let solids = &world.data.level.solids;
+        let sight = world.sight();
+        self.sight = Some((sight, sight.outline(solids)));
+        //pickups glow steadily as long as they lie around
+        let pickups = world.pickups.iter().map(|pickup| {
+            let color = world.data.pickups.get(pickup.kind).color;
+            Light { pos: pickup.pos, radius: PICKUP_LIGHT_RADIUS, color, ticks_left: 1, lifetime: 1 }
+        });
+        //an outline only depends on where a light is and how far it reaches, so
+        //the ones from last frame are reused for lights that haven't moved
+        let mut last = std::mem::take(&mut self.shining);
+        self.shining = self.lights.iter().copied().chain(pickups)
+            .map(|light| {
+                let sight = Sight { origin: light.pos, facing: 0f32, half_angle: PI, range: light.radius, ambient: light.radius };
+                let outline = match last.iter().position(|shining| shining.sight == sight) {
+                    Some(index) => last.swap_remove(index).outline,
+                    None => sight.outline(solids),
+                };
+                Shining { light, sight, outline }
+            })
+            .collect();
+        if self.software {
+            return Ok(());
+        }
+
+        let mask = self.mask
+            .get_or_insert_with(|| Image::new_canvas_image(ctx, ctx.gfx.surface_format(), WIN_WIDTH as u32, WIN_HEIGHT as u32, 1))
+            .clone();
+        let mut canvas = Canvas::from_image(ctx, mask, Color::new(0f32, 0f32, 0f32, FOG_DARKNESS));
+        canvas.set_screen_coordinates(Rect::new(view.x, view.y, WIN_WIDTH, WIN_HEIGHT));
+        //the sight overwrites the darkness instead of blending into it, and
+        //gets darker towards the far end of the cone
+        canvas.set_blend_mode(BlendMode::REPLACE);
+        if let Some((sight, outline)) = &self.sight {
+            let furthest = sight.range.max(sight.ambient);
+            canvas.draw(&fan(ctx, sight.origin, furthest, outline, |share| [0f32, 0f32, 0f32, FOG_DARKNESS * share * share]), DrawParam::default());
+        }
+        canvas.set_blend_mode(CARVE);
+        for shining in &self.shining {
+            let strength = FOG_DARKNESS * shining.light.strength();
+            canvas.draw(&fan(ctx, shining.light.pos, shining.light.radius, &shining.outline, |share| [0f32, 0f32, 0f32, strength * (1f32 - share)]), DrawParam::default());
+        }
+        canvas.finish(ctx)
+    }
+
+    /// Tints what the lights shine on and darkens the rest, on `canvas`, which
+    /// is in world coordinates.
+    pub fn draw(&self, ctx: &Context, canvas: &mut graphics::Canvas, world: &World, view: Vec2) {
+        canvas.set_blend_mode(BlendMode::ADD);
+        for shining in &self.shining {
+            let [r, g, b] = shining.light.color;
+            let strength = GLOW * shining.light.strength();
+            canvas.draw(&fan(ctx, shining.light.pos, shining.light.radius, &shining.outline, |share| [r, g, b, strength * (1f32 - share)]), DrawParam::default());
+        }
+        canvas.set_blend_mode(BlendMode::ALPHA);
+
+        if self.software {
+            canvas.draw(&self.software_mask(ctx, world, view), DrawParam::default());
+        } else if let Some(mask) = &self.mask {
+            canvas.draw(mask, DrawParam::default().dest(view));
+        }
+    }
+
+    /// The darkness as a grid over the window, with how lit each corner is
+    /// worked out on the CPU.
+    fn software_mask(&self, ctx: &Context, world: &World, view: Vec2) -> Mesh {
+        let solids = &world.data.level.solids;
+        let columns = (WIN_WIDTH / SOFTWARE_CELL).ceil() as u32;
+        let rows = (WIN_HEIGHT / SOFTWARE_CELL).ceil() as u32;
+        let mut vertices = Vec::new();
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let pos = view + Vec2::new(column as f32, row as f32) * SOFTWARE_CELL;
+                let mut light = 0f32;
+                if let Some((sight, _)) = &self.sight {
+                    let share = pos.distance(sight.origin) / sight.range.max(sight.ambient);
+                    if sight.sees(pos, solids) {
+                        light += 1f32 - share * share;
+                    }
+                }
+                for shining in &self.shining {
+                    if shining.sight.sees(pos, solids) {
+                        light += shining.light.strength() * (1f32 - pos.distance(shining.light.pos) / shining.light.radius);
+                    }
+                }
+                vertices.push(vertex(pos, [0f32, 0f32, 0f32, FOG_DARKNESS * (1f32 - light.clamp(0f32, 1f32))]));
+            }
+        }
+        let mut indices = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let corner = row * (columns + 1) + column;
+                let below = corner + columns + 1;
+                indices.extend([corner, corner + 1, below, corner + 1, below + 1, below]);
+            }
+        }
+        Mesh::from_data(ctx, MeshData { vertices: &vertices, indices: &indices })
+    }
+}
+
+fn vertex(pos: Vec2, color: [f32; 4]) -> Vertex {
+    Vertex { position: pos.into(), uv: [0f32; 2], color }
+}
+
+/// Triangles fanning out from `center` to `outline`, colored by `color` for
+/// the share of `radius` each point is away from the center.
+fn fan(ctx: &Context, center: Vec2, radius: f32, outline: &[Vec2], color: impl Fn(f32) -> [f32; 4]) -> Mesh {
+    let mut vertices = vec![vertex(center, color(0f32))];
+    vertices.extend(outline.iter().map(|point| vertex(*point, color((point.distance(center) / radius).min(1f32)))));
+    let corners = outline.len() as u32;
+    let indices: Vec<u32> = (0..corners).flat_map(|corner| [0, corner + 1, (corner + 1) % corners + 1]).collect();
+    Mesh::from_data(ctx, MeshData { vertices: &vertices, indices: &indices })
+}
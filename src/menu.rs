@@ -0,0 +1,188 @@
+use ggez::{
+    event::MouseButton,
+    graphics::{self, Color},
+    glam::Vec2,
+    input::keyboard::{KeyInput, KeyCode},
+    Context,
+    GameResult,
+};
+
+use crate::screens::{Screen, Shared, Transition};
+use crate::world::GameMode;
+use crate::ui::{Ui, WidgetId, Anchor, Direction};
+use crate::MainState;
+
+/// What happens when a menu item is activated.
+#[derive(Clone, Copy)]
+enum Action {
+    Open(fn() -> Menu),
+    Play(GameMode),
+    ToggleFps,
+    Volume,
+    Back,
+    Quit,
+}
+
+/// A front-end screen: a title, some lines of text and a list of buttons that
+/// can be picked with the keyboard or the mouse.
+pub struct Menu {
+    ui: Ui,
+    buttons: Vec<(WidgetId, &'static str, Action)>,
+}
+
+impl Menu {
+    fn new(title: &'static str, lines: Vec<&'static str>, items: Vec<(&'static str, Action)>) -> Menu {
+        let mut ui = Ui::new();
+        let root = ui.root(Anchor::Center, Direction::Column, None);
+        ui.label(root, title, 64f32, Color::YELLOW);
+        if !lines.is_empty() {
+            let text = ui.panel(root, Direction::Column, None);
+            for line in lines {
+                ui.label(text, line, 24f32, Color::WHITE);
+            }
+        }
+        let buttons: Vec<_> = items.into_iter()
+            .map(|(label, action)| (ui.button(root, label, 32f32), label, action))
+            .collect();
+        ui.set_focus(buttons[0].0);
+        Menu { ui, buttons }
+    }
+
+    pub fn main() -> Menu {
+        Menu::new("ZOMBIE CHASED", Vec::new(), vec![
+            ("Play", Action::Open(Menu::mode_select)),
+            ("Options", Action::Open(Menu::options)),
+            ("Controls", Action::Open(Menu::controls)),
+            ("Credits", Action::Open(Menu::credits)),
+            ("Quit", Action::Quit),
+        ])
+    }
+
+    fn mode_select() -> Menu {
+        Menu::new("SELECT MODE", vec![
+            "Classic: 100 HP and 199 dollars to start with",
+            "Hardcore: 25 HP and not a single dollar",
+        ], vec![
+            ("Classic", Action::Play(GameMode::Classic)),
+            ("Hardcore", Action::Play(GameMode::Hardcore)),
+            ("Back", Action::Back),
+        ])
+    }
+
+    fn options() -> Menu {
+        Menu::new("OPTIONS", Vec::new(), vec![
+            ("Show FPS", Action::ToggleFps),
+            ("Volume", Action::Volume),
+            ("Back", Action::Back),
+        ])
+    }
+
+    fn controls() -> Menu {
+        Menu::new("CONTROLS", vec![
+            "W A S D: move",
+            "mouse: aim",
+            "Space: fire",
+            "R: reload",
+            "P: pause and open the gun shop",
+            "1 to 9: buy or pick a gun while paused or between waves",
+            "R after dying: start a new round",
+            "Escape: back to the menu",
+        ], vec![("Back", Action::Back)])
+    }
+
+    fn credits() -> Menu {
+        Menu::new("CREDITS", vec![
+            "made by cstn",
+            "built with ggez",
+        ], vec![("Back", Action::Back)])
+    }
+
+    /// Brings the labels of the option buttons up to date.
+    fn refresh(&mut self, shared: &Shared) {
+        for (id, label, action) in &self.buttons {
+            let text = match action {
+                Action::ToggleFps => format!("{label}: {}", if shared.preferences.show_fps { "on" } else { "off" }),
+                Action::Volume => format!("{label}: {}%", (shared.preferences.volume * 100f32).round()),
+                _ => continue,
+            };
+            self.ui.set_text(*id, text);
+        }
+    }
+
+    fn activate(&mut self, shared: &mut Shared, button: WidgetId) -> Transition {
+        let action = match self.buttons.iter().find(|(id, _, _)| *id == button) {
+            Some((_, _, action)) => *action,
+            None => return Transition::None,
+        };
+        match action {
+            Action::Open(menu) => Transition::Push(Box::new(menu())),
+            Action::Play(mode) => Transition::Push(Box::new(MainState::start(shared, mode))),
+            Action::ToggleFps => {
+                shared.preferences.show_fps = !shared.preferences.show_fps;
+                Transition::None
+            }
+            Action::Volume => {
+                //cycle through 0%, 25%, ... 100%
+                let steps = (shared.preferences.volume * 4f32).round() as u8;
+                shared.preferences.volume = ((steps + 1) % 5) as f32 / 4f32;
+                Transition::None
+            }
+            Action::Back => Transition::Pop,
+            Action::Quit => Transition::Quit,
+        }
+    }
+}
+
+impl Screen for Menu {
+    fn draw(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult {
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::from_rgb(0,26,17));
+
+        self.refresh(shared);
+        self.ui.draw(ctx, &mut canvas)?;
+
+        //draw cursor
+        let cursor_param = graphics::DrawParam::default()
+            .dest(shared.mouse_pos)
+            .scale(Vec2::new(2.5f32, 2.5f32))
+            .offset(Vec2::new(0.5, 0.5));
+        canvas.draw(shared.assets.image(shared.hud.cursor), cursor_param);
+
+        canvas.finish(ctx)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, input: KeyInput) -> GameResult<Transition> {
+        let transition = match input.keycode {
+            Some(KeyCode::W) => {
+                self.ui.move_focus(-1);
+                Transition::None
+            }
+            Some(KeyCode::S) => {
+                self.ui.move_focus(1);
+                Transition::None
+            }
+            Some(KeyCode::Space) => match self.ui.focused() {
+                Some(button) => self.activate(shared, button),
+                None => Transition::None,
+            },
+            Some(KeyCode::Escape) => Transition::Pop,
+            Some(key) => match self.ui.key_down(key) {
+                Some(button) => self.activate(shared, button),
+                None => Transition::None,
+            },
+            None => Transition::None,
+        };
+        Ok(transition)
+    }
+
+    fn mouse_motion(&mut self, _ctx: &mut Context, _shared: &mut Shared, pos: Vec2) -> GameResult<Transition> {
+        self.ui.mouse_motion(pos);
+        Ok(Transition::None)
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, pos: Vec2) -> GameResult<Transition> {
+        match self.ui.mouse_down(button, pos) {
+            Some(button) => Ok(self.activate(shared, button)),
+            None => Ok(Transition::None),
+        }
+    }
+}
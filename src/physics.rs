@@ -0,0 +1,95 @@
+use ggez::glam::Vec2;
+
+/// How far a box that ran into a solid is kept off its side, so rounding never
+/// leaves it inside.
+const SKIN: f32 = 0.01;
+
+/// Axis aligned box, by its corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    /// A box with its top left corner at `pos`.
+    pub fn new(pos: Vec2, size: Vec2) -> Aabb {
+        Aabb { min: pos, max: pos + size }
+    }
+
+    pub fn around(center: Vec2, half: Vec2) -> Aabb {
+        Aabb { min: center - half, max: center + half }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x && other.min.x < self.max.x
+            && self.min.y < other.max.y && other.min.y < self.max.y
+    }
+
+    fn grown(&self, half: Vec2) -> Aabb {
+        Aabb { min: self.min - half, max: self.max + half }
+    }
+}
+
+/// Where along `delta` a point starting at `start` enters `target`, as a
+/// fraction of `delta`, and the normal of the side it enters through. A point
+/// that starts inside never enters, so whatever got stuck can walk out.
+fn entry(start: Vec2, delta: Vec2, target: &Aabb) -> Option<(f32, Vec2)> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut leave = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+    for axis in 0..2 {
+        let (from, by) = (start[axis], delta[axis]);
+        let (min, max) = (target.min[axis], target.max[axis]);
+        if by == 0f32 {
+            if from <= min || from >= max {
+                return None;
+            }
+            continue;
+        }
+        let (near, far) = if by > 0f32 { ((min - from) / by, (max - from) / by) } else { ((max - from) / by, (min - from) / by) };
+        if near > enter {
+            enter = near;
+            normal = Vec2::ZERO;
+            normal[axis] = -by.signum();
+        }
+        leave = leave.min(far);
+    }
+    if enter > leave || !(0f32..=1f32).contains(&enter) {
+        return None;
+    }
+    Some((enter, normal))
+}
+
+/// The first of `solids` that a box with `half` extents runs into when moving
+/// from `pos` by `delta`: the fraction of `delta` it gets to travel and the
+/// normal of the side it hits.
+pub fn sweep(pos: Vec2, half: Vec2, delta: Vec2, solids: &[Aabb]) -> Option<(f32, Vec2)> {
+    solids.iter()
+        .filter_map(|solid| entry(pos, delta, &solid.grown(half)))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// Moves a box with `half` extents from `pos` by `delta`. Instead of passing
+/// into one of `solids` it stops at its side and slides along it with what is
+/// left of the movement. Also tells whether it hit anything.
+pub fn move_and_slide(mut pos: Vec2, half: Vec2, mut delta: Vec2, solids: &[Aabb]) -> (Vec2, bool) {
+    let mut hit = false;
+    //sliding into a corner takes a second stop
+    for _ in 0..3 {
+        match sweep(pos, half, delta, solids) {
+            None => return (pos + delta, hit),
+            Some((fraction, normal)) => {
+                hit = true;
+                pos += delta * fraction + normal * SKIN;
+                let rest = delta * (1f32 - fraction);
+                delta = rest - normal * rest.dot(normal);
+            }
+        }
+    }
+    (pos, hit)
+}
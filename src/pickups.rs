@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use enum_map::Enum;
+
+use ggez::glam::Vec2;
+
+use crate::assets::{Manifest, SoundHandle};
+use crate::config::check;
+
+/// File in the resources directory that defines every kind of pickup.
+pub const PICKUPS_FILE: &str = "pickups.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PickupsFile {
+    pickup: Vec<PickupDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PickupDef {
+    name: String,
+    color: [f32; 3],
+    lifetime: u16,
+    sound: String,
+    cash: Option<u16>,
+    health: Option<u16>,
+    ammo: Option<u16>,
+    power_up: Option<PowerUp>,
+    duration: Option<u16>,
+}
+
+/// Something that helps the player for a while after picking it up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUp {
+    /// Fires twice as often.
+    RapidFire,
+    /// Bullets go through the enemies they hit.
+    Piercing,
+    /// Walks faster.
+    Speed,
+    /// Takes no damage.
+    Invulnerable,
+}
+
+impl PowerUp {
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUp::RapidFire => "rapid fire",
+            PowerUp::Piercing => "piercing rounds",
+            PowerUp::Speed => "speed boost",
+            PowerUp::Invulnerable => "invulnerable",
+        }
+    }
+}
+
+/// What picking one up does.
+#[derive(Debug, Clone, Copy)]
+pub enum Effect {
+    /// Dollars.
+    Cash(u16),
+    /// Health, up to what the player started with.
+    Health(u16),
+    /// Magazines of spare rounds for the gun in hand, or else an owned gun
+    /// that runs out of them.
+    Ammo(u16),
+    /// A power-up lasting the given number of ticks.
+    PowerUp(PowerUp, u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PickupKindId(u16);
+
+#[derive(Debug)]
+pub struct PickupKind {
+    pub name: String,
+    /// Color it is drawn and glows in.
+    pub color: [f32; 3],
+    /// Ticks it lies around before it disappears.
+    pub lifetime: u16,
+    /// Played when it is picked up.
+    pub sound: SoundHandle,
+    pub effect: Effect,
+}
+
+/// A pickup lying in the arena.
+#[derive(Debug, Clone)]
+pub struct Pickup {
+    pub kind: PickupKindId,
+    pub pos: Vec2,
+    pub ticks_left: u16,
+}
+
+/// Every kind of pickup the game knows, by name.
+#[derive(Debug)]
+pub struct PickupKinds {
+    kinds: Vec<PickupKind>,
+}
+
+impl PickupKinds {
+    pub fn load(resources_dir: &Path, manifest: &mut Manifest) -> Result<PickupKinds, String> {
+        let path = resources_dir.join(PICKUPS_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        PickupKinds::parse(&text, manifest).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str, manifest: &mut Manifest) -> Result<PickupKinds, String> {
+        let file: PickupsFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        let mut kinds: Vec<PickupKind> = Vec::new();
+        for def in file.pickup {
+            if kinds.iter().any(|kind| kind.name == def.name) {
+                return Err(format!("pickup `{}` is defined twice", def.name));
+            }
+            let in_pickup = |err: String| format!("pickup `{}`: {err}", def.name);
+            check("lifetime", def.lifetime, 1, 60000).map_err(in_pickup)?;
+            let effect = match (def.cash, def.health, def.ammo, def.power_up, def.duration) {
+                (Some(cash), None, None, None, None) => Effect::Cash(cash),
+                (None, Some(health), None, None, None) => Effect::Health(health),
+                (None, None, Some(ammo), None, None) => Effect::Ammo(ammo),
+                (None, None, None, Some(power_up), Some(duration)) => {
+                    check("duration", duration, 1, 60000).map_err(in_pickup)?;
+                    Effect::PowerUp(power_up, duration)
+                }
+                (None, None, None, Some(_), None) => return Err(in_pickup("a power_up needs a duration".to_string())),
+                _ => return Err(in_pickup("needs exactly one of cash, health, ammo or power_up (with a duration)".to_string())),
+            };
+            kinds.push(PickupKind { name: def.name, color: def.color, lifetime: def.lifetime, sound: manifest.sound(&def.sound), effect });
+        }
+        Ok(PickupKinds { kinds })
+    }
+
+    pub fn id(&self, name: &str) -> Option<PickupKindId> {
+        self.kinds.iter().position(|kind| kind.name == name).map(|index| PickupKindId(index as u16))
+    }
+
+    pub fn get(&self, id: PickupKindId) -> &PickupKind {
+        &self.kinds[id.0 as usize]
+    }
+}
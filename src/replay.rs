@@ -0,0 +1,196 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::input::{InputEvent, key_name, key_from_name};
+use crate::world::GameMode;
+
+/// First line of every replay file. Bump the number whenever the format or the
+/// simulation changes in a way that makes older recordings play out differently.
+const HEADER: &str = "zombie-chased-replay";
+const VERSION: u32 = 15;
+
+/// Every input event of a round, stamped with the tick it arrived before,
+/// plus the seed, mode and config the round was started with.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub config: Config,
+    pub events: Vec<(u64, InputEvent)>,
+    /// Configs hot-reloaded during the round, stamped with the tick they
+    /// applied from.
+    pub reloads: Vec<(u64, Config)>,
+    /// Number of ticks the recorded round lasted.
+    pub length: u64,
+}
+
+impl Replay {
+    pub fn new(seed: u64, mode: GameMode, config: Config) -> Replay {
+        Replay { seed, mode, config, events: Vec::new(), reloads: Vec::new(), length: 0 }
+    }
+
+    pub fn record(&mut self, tick: u64, event: InputEvent) {
+        if event.is_relevant() {
+            self.events.push((tick, event));
+        }
+    }
+
+    /// Notes that `config` applies from `tick` on; a later reload before the
+    /// same tick replaces it.
+    pub fn reload(&mut self, tick: u64, config: Config) {
+        if self.reloads.last().is_some_and(|(last, _)| *last == tick) {
+            self.reloads.pop();
+        }
+        self.reloads.push((tick, config));
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut out = String::new();
+        writeln!(out, "{HEADER} {VERSION}").unwrap();
+        writeln!(out, "seed {}", self.seed).unwrap();
+        writeln!(out, "mode {}", self.mode.name()).unwrap();
+        //configs are stored as their TOML, one `config` line per setting or
+        //table header
+        for line in toml_lines(&self.config) {
+            writeln!(out, "config {line}").unwrap();
+        }
+        for (tick, event) in &self.events {
+            match event {
+                InputEvent::KeyDown(key) => writeln!(out, "{tick} down {}", key_name(*key).unwrap()),
+                InputEvent::KeyUp(key) => writeln!(out, "{tick} up {}", key_name(*key).unwrap()),
+                InputEvent::MouseMotion(x, y) => writeln!(out, "{tick} mouse {x} {y}"),
+            }.unwrap();
+        }
+        for (tick, config) in &self.reloads {
+            for line in toml_lines(config) {
+                writeln!(out, "{tick} config {line}").unwrap();
+            }
+        }
+        writeln!(out, "end {}", self.length).unwrap();
+        std::fs::write(path, out)
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("could not read replay {}: {err}", path.display()))?;
+        Replay::parse(&text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines().enumerate();
+
+        let header = lines.next().map(|(_, line)| line).unwrap_or_default();
+        match header.split_once(' ') {
+            Some((HEADER, version)) => {
+                let version: u32 = version.trim().parse().map_err(|_| format!("bad replay version `{version}`"))?;
+                if version != VERSION {
+                    return Err(format!("replay version {version} is not supported (expected {VERSION})"));
+                }
+            }
+            _ => return Err("not a replay file".to_string()),
+        }
+
+        let mut replay = Replay::new(0, GameMode::Classic, Config::default());
+        let mut config = String::new();
+        let mut reloads: Vec<(u64, String)> = Vec::new();
+        for (nr, line) in lines {
+            let line_err = |msg: &str| format!("line {}: {msg}", nr + 1);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            //everything after `config` is a line of TOML
+            let setting = || line.split_once("config").unwrap().1.trim();
+            match words.as_slice() {
+                [] => continue,
+                ["config", ..] => writeln!(config, "{}", setting()).unwrap(),
+                [tick, "config", ..] => {
+                    let tick: u64 = tick.parse().map_err(|_| line_err("bad tick"))?;
+                    match reloads.last_mut() {
+                        Some((last, text)) if *last == tick => writeln!(text, "{}", setting()).unwrap(),
+                        _ => reloads.push((tick, format!("{}\n", setting()))),
+                    }
+                }
+                ["seed", seed] => replay.seed = seed.parse().map_err(|_| line_err("bad seed"))?,
+                ["mode", mode] => replay.mode = GameMode::from_name(mode).ok_or_else(|| line_err("unknown mode"))?,
+                ["end", length] => replay.length = length.parse().map_err(|_| line_err("bad length"))?,
+                [tick, kind, args @ ..] => {
+                    let tick: u64 = tick.parse().map_err(|_| line_err("bad tick"))?;
+                    let event = match (*kind, args) {
+                        ("down", [key]) => InputEvent::KeyDown(key_from_name(key).ok_or_else(|| line_err("unknown key"))?),
+                        ("up", [key]) => InputEvent::KeyUp(key_from_name(key).ok_or_else(|| line_err("unknown key"))?),
+                        ("mouse", [x, y]) => InputEvent::MouseMotion(
+                            x.parse().map_err(|_| line_err("bad mouse x"))?,
+                            y.parse().map_err(|_| line_err("bad mouse y"))?,
+                        ),
+                        _ => return Err(line_err("unknown event")),
+                    };
+                    replay.events.push((tick, event));
+                }
+                _ => return Err(line_err("unknown entry")),
+            }
+        }
+        replay.config = Config::parse(&config).map_err(|err| format!("config: {err}"))?;
+        for (tick, text) in reloads {
+            let config = Config::parse(&text).map_err(|err| format!("config reloaded at tick {tick}: {err}"))?;
+            replay.reloads.push((tick, config));
+        }
+        Ok(replay)
+    }
+}
+
+/// The lines of `config` as TOML, without the blank ones between tables.
+fn toml_lines(config: &Config) -> Vec<String> {
+    toml::to_string(config).unwrap().lines().filter(|line| !line.is_empty()).map(String::from).collect()
+}
+
+/// Hands out the recorded events of a replay tick by tick.
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+    next_reload: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback { replay, next: 0, next_reload: 0 }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.replay.mode
+    }
+
+    pub fn length(&self) -> u64 {
+        self.replay.length
+    }
+
+    /// The config the round was started with.
+    pub fn config(&self) -> &Config {
+        &self.replay.config
+    }
+
+    /// The config reloaded before `tick` was stepped, if any.
+    pub fn reload_for(&mut self, tick: u64) -> Option<Config> {
+        let (reloaded, config) = self.replay.reloads.get(self.next_reload)?;
+        if *reloaded > tick {
+            return None;
+        }
+        self.next_reload += 1;
+        Some(config.clone())
+    }
+
+    /// Events that arrived before `tick` was stepped.
+    pub fn events_for(&mut self, tick: u64) -> impl Iterator<Item = InputEvent> + '_ {
+        let start = self.next;
+        while self.next < self.replay.events.len() && self.replay.events[self.next].0 <= tick {
+            self.next += 1;
+        }
+        self.replay.events[start..self.next].iter().map(|(_, event)| *event)
+    }
+
+    pub fn finished(&self, tick: u64) -> bool {
+        tick >= self.replay.length
+    }
+}
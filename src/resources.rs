@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+/// Environment variable that can point at the resources directory.
+pub const RESOURCES_ENV: &str = "ZOMBIE_CHASED_RESOURCES";
+
+/// Finds the directory holding the game's images and sounds. Looks, in order,
+/// at the `--resources` flag, `$ZOMBIE_CHASED_RESOURCES`, a `resources`
+/// directory next to the executable and the one in the source checkout. A
+/// `--resources` flag that doesn't point at a directory is an error rather
+/// than a reason to look elsewhere, so a typo can't load other resources.
+pub fn find_resources_dir(from_cli: Option<&Path>) -> Result<PathBuf, String> {
+    let mut candidates: Vec<(&str, PathBuf)> = Vec::new();
+    if let Some(path) = from_cli {
+        candidates.push(("--resources", path.to_path_buf()));
+    }
+    if let Some(path) = std::env::var_os(RESOURCES_ENV) {
+        candidates.push((RESOURCES_ENV, PathBuf::from(path)));
+    }
+    if let Some(exe_dir) = std::env::current_exe().ok().as_deref().and_then(Path::parent) {
+        candidates.push(("next to the executable", exe_dir.join("resources")));
+    }
+    candidates.push(("source checkout", Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")));
+
+    //with --resources only that one is looked at, the others are still listed
+    //in the error so it shows where the game would have looked without it
+    let searched = if from_cli.is_some() { &candidates[..1] } else { &candidates[..] };
+    if let Some((_, path)) = searched.iter().find(|(_, path)| path.is_dir()) {
+        return Ok(path.clone());
+    }
+
+    let mut msg = match from_cli {
+        Some(path) => format!("the resources directory {} given with --resources does not exist, candidates:", path.display()),
+        None => String::from("could not find the resources directory, tried:"),
+    };
+    for (source, path) in &candidates {
+        msg.push_str(&format!("\n  {} ({source})", path.display()));
+    }
+    msg.push_str(&format!("\npass --resources <dir> or set {RESOURCES_ENV} to point at it"));
+    Err(msg)
+}
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use ggez::{
+    event::{self, MouseButton},
+    glam::Vec2,
+    input::keyboard::KeyInput,
+    audio::SoundSource,
+    Context,
+    GameResult,
+};
+
+use crate::assets::{Assets, SoundHandle};
+use crate::settings::TICKS_PER_SECOND;
+use crate::config::{Config, ConfigWatcher};
+use crate::data::GameData;
+use crate::HudSprites;
+
+/// Player choices made in the options screen.
+#[derive(Debug, Clone)]
+pub struct Preferences {
+    pub show_fps: bool,
+    /// 0 is silent, 1 is full volume.
+    pub volume: f32,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences { show_fps: true, volume: 1f32 }
+    }
+}
+
+/// State that outlives any single screen.
+pub struct Shared {
+    pub assets: Assets,
+    pub hud: HudSprites,
+    pub data: Rc<GameData>,
+    pub config: Config,
+    pub config_watcher: ConfigWatcher,
+    pub preferences: Preferences,
+    pub record_path: PathBuf,
+    /// Seed forced from the command line, used for the first round only.
+    pub seed: Option<u64>,
+    pub mouse_pos: Vec2,
+    /// Draw the lighting on the CPU, for software renderers.
+    pub software_lighting: bool,
+}
+
+impl Shared {
+    pub fn play_sound(&mut self, ctx: &Context, sound: SoundHandle) -> GameResult {
+        self.play_sound_at(ctx, sound, 1f32)
+    }
+
+    /// Plays `sound` at `loudness` times the volume the player picked.
+    pub fn play_sound_at(&mut self, ctx: &Context, sound: SoundHandle, loudness: f32) -> GameResult {
+        let volume = self.preferences.volume * loudness;
+        let source = self.assets.sound(sound);
+        source.set_volume(volume);
+        source.play(ctx)
+    }
+
+    /// Seed for a new round; printed so the round can be reproduced.
+    pub fn next_seed(&mut self) -> u64 {
+        let seed = self.seed.take().unwrap_or_else(rand::random);
+        println!("seed: {seed}");
+        seed
+    }
+}
+
+/// What the screen stack should do after a screen handled an event.
+pub enum Transition {
+    None,
+    Push(Box<dyn Screen>),
+    Pop,
+    Quit,
+}
+
+/// One screen of the game: a menu, the gameplay, ... Only the topmost screen
+/// on the stack gets updated, drawn and sees input.
+pub trait Screen {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult;
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, _input: KeyInput) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn key_up(&mut self, _ctx: &mut Context, _shared: &mut Shared, _input: KeyInput) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn mouse_motion(&mut self, _ctx: &mut Context, _shared: &mut Shared, _pos: Vec2) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, _button: MouseButton, _pos: Vec2) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    /// Called when the screen is popped or the game is closed while it is on the stack.
+    fn leave(&mut self, _shared: &mut Shared) {}
+}
+
+pub struct ScreenStack {
+    screens: Vec<Box<dyn Screen>>,
+    shared: Shared,
+}
+
+impl ScreenStack {
+    pub fn new(shared: Shared, screens: Vec<Box<dyn Screen>>) -> ScreenStack {
+        ScreenStack { screens, shared }
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => (),
+            Transition::Push(screen) => self.screens.push(screen),
+            Transition::Pop => {
+                if let Some(mut screen) = self.screens.pop() {
+                    screen.leave(&mut self.shared);
+                }
+            }
+            Transition::Quit => ctx.request_quit(),
+        }
+        if self.screens.is_empty() {
+            ctx.request_quit();
+        }
+    }
+}
+
+impl event::EventHandler<ggez::GameError> for ScreenStack {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.update(ctx, &mut self.shared)?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+        //screens that don't run the simulation must not leave time behind, or
+        //the next round would start with a burst of catch-up ticks
+        while ctx.time.check_update_time(TICKS_PER_SECOND) {}
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        if let Some(screen) = self.screens.last_mut() {
+            screen.draw(ctx, &mut self.shared)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _: f32, _: f32) -> GameResult {
+        self.shared.mouse_pos = Vec2::new(x, y);
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.mouse_motion(ctx, &mut self.shared, Vec2::new(x, y))?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+        Ok(())
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.mouse_down(ctx, &mut self.shared, button, Vec2::new(x, y))?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> GameResult {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.key_down(ctx, &mut self.shared, input)?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+        Ok(())
+    }
+
+    fn key_up_event(&mut self, ctx: &mut Context, input: KeyInput) -> GameResult {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.key_up(ctx, &mut self.shared, input)?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        while let Some(mut screen) = self.screens.pop() {
+            screen.leave(&mut self.shared);
+        }
+        Ok(false)
+    }
+}
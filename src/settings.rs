@@ -1,23 +1,49 @@
+// Speeds are in pixels per tick and times in ticks; the simulation always runs
+// at TICKS_PER_SECOND regardless of the display's refresh rate.
+pub const TICKS_PER_SECOND: u32 = 60;
 pub const WIN_HEIGHT: f32 = 720f32;
 pub const WIN_WIDTH: f32 = 1280f32;
 pub const PLAYER_HEIGHT: f32 = 20f32 * 1.5;
 pub const PLAYER_WIDTH: f32 = 40f32 * 2f32;
+/// Half the size of the box the player bumps into walls with.
+pub const PLAYER_SOLID_HALF: f32 = 20f32;
+/// Pickups this close to the player are picked up.
+pub const PICKUP_RADIUS: f32 = 28f32;
+/// Side of the diamond a pickup is drawn as.
+pub const PICKUP_SIZE: f32 = 14f32;
+/// Pickups blink for the last this many ticks before they disappear.
+pub const PICKUP_BLINK: u16 = 2 * TICKS_PER_SECOND as u16;
+/// How much faster the player walks with the speed boost.
+pub const SPEED_BOOST: f32 = 1.5;
+/// Ticks before a piercing bullet can hurt again, so it doesn't hit the enemy
+/// it is passing through twice.
+pub const PIERCE_GRACE: u16 = 4;
+/// How dark the parts of the world the player can't see are, from 0 to 1.
+pub const FOG_DARKNESS: f32 = 0.85;
+
+// Defaults for the tuning values that config.toml can override, see config.rs.
 pub const PX_MOVEMENT: f32 = 5f32;
-pub const BULLET_SPEED: f32 = 20f32;
 pub const BULLET_HEIGHT: f32 = 7f32 * 3f32;
 pub const BULLET_WIDTH: f32 = 3f32 * 3f32;
-pub const PISTOL_RELOAD_TIME: u16 = 20;
-pub const MG_RELOAD_TIME: u16 = 10;
 pub const PI: f32 = std::f32::consts::PI;
-pub const ENEMY_SPEED: f32 = 1.5f32;
-pub const ENEMY_COOLDOWN: u16 = 15;
-pub const BULLET_TIME: u16 = 45;
-pub const BULLETS_SHOT: u16 = 1;
-pub const BULLETS_ANGLE: f32 = 0f32;
-pub const ENEMY_HEIGHT: f32 = 20f32;
+/// Ticks an enemy stays tinted after being hit.
+pub const HIT_FLASH_TIME: u16 = 6;
 pub const ENEMY_WIDTH: f32 = 40f32;
-pub const FOG_DISTANCE: f32 = 600f32;
-pub const ENEMY_FRAME_TIME: u8 = 5;
-pub const PLAYER_FRAME_TIME: u8 = 5;
+pub const VISION_RANGE: f32 = 700f32;
+pub const VISION_ANGLE: f32 = PI / 2f32;
+pub const AMBIENT_RADIUS: f32 = 140f32;
+pub const HEARING_DISTANCE: f32 = 900f32;
+pub const MAGNET_RADIUS: f32 = 150f32;
+pub const MAGNET_SPEED: f32 = 8f32;
 pub const PARTICLE_HEALTH: u16 = 60;
-pub const PARTICLE_ANGLE: f32 = PI / 6f32;
\ No newline at end of file
+pub const PARTICLE_ANGLE: f32 = PI / 6f32;
+pub const NEIGHBOUR_RADIUS: f32 = 60f32;
+pub const SEPARATION_WEIGHT: f32 = 1.5;
+pub const ALIGNMENT_WEIGHT: f32 = 0.3;
+pub const ARRIVAL_RADIUS: f32 = 40f32;
+pub const STEERING_FORCE: f32 = 0.25;
+pub const CAMERA_SMOOTHING: f32 = 0.1;
+pub const ADAPTIVE_DIFFICULTY: bool = false;
+pub const INTENSITY_LOW: f32 = 0.35;
+pub const INTENSITY_HIGH: f32 = 0.65;
+pub const SPAWN_INTERVAL_SCALE: f32 = 1f32;
\ No newline at end of file
@@ -0,0 +1,33 @@
+use ggez::glam::Vec2;
+
+/// Velocity that heads for `target` at `speed`, slowing down within
+/// `slowing_radius` of it so it comes to rest there instead of overshooting.
+pub fn arrive(pos: Vec2, target: Vec2, speed: f32, slowing_radius: f32) -> Vec2 {
+    let offset = target - pos;
+    let dist = offset.length();
+    if dist == 0f32 {
+        return Vec2::ZERO;
+    }
+    let speed = if dist < slowing_radius { speed * dist / slowing_radius } else { speed };
+    offset / dist * speed
+}
+
+/// Push away from neighbours closer than `radius`, from 0 at the edge of the
+/// radius to 1 on top of a neighbour. `offsets` point from each neighbour to
+/// the one being steered.
+pub fn separation(offsets: impl Iterator<Item = Vec2>, radius: f32) -> Vec2 {
+    offsets
+        .filter_map(|offset| {
+            let dist = offset.length();
+            (dist > 0f32 && dist < radius).then(|| offset / dist * (1f32 - dist / radius))
+        })
+        .sum()
+}
+
+/// Change of `velocity` that matches the average velocity of the neighbours.
+pub fn alignment(velocity: Vec2, neighbours: &[Vec2]) -> Vec2 {
+    if neighbours.is_empty() {
+        return Vec2::ZERO;
+    }
+    neighbours.iter().sum::<Vec2>() / neighbours.len() as f32 - velocity
+}
@@ -0,0 +1,416 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+
+/// Bits of a tile id that tell how the tile is flipped, which we don't draw.
+const FLIP_FLAGS: u32 = 0xf000_0000;
+
+/// A map made in the Tiled editor, saved as .tmx or as .tmj/.json, with just the
+/// parts the game reads. Tile data has to be saved as CSV and tilesets have to
+/// be embedded in the map.
+#[derive(Debug)]
+pub struct TileMap {
+    /// Width and height, in tiles.
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<Tileset>,
+    /// Bottom layer first. Layers inside groups come in the place of their group.
+    pub layers: Vec<Layer>,
+}
+
+/// A tileset cut from a single image.
+#[derive(Debug)]
+pub struct Tileset {
+    /// Id of the first tile in the map data; the others follow it in order.
+    pub first_id: u32,
+    /// Path of the image, relative to the map file.
+    pub image: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_count: u32,
+    pub columns: u32,
+    /// Pixels around the tiles at the edges of the image.
+    pub margin: u32,
+    /// Pixels between two tiles in the image.
+    pub spacing: u32,
+}
+
+impl Tileset {
+    /// Pixel position of tile `index` of this set within its image.
+    pub fn source(&self, index: u32) -> (u32, u32) {
+        let (column, row) = (index % self.columns, index / self.columns);
+        (self.margin + column * (self.tile_width + self.spacing), self.margin + row * (self.tile_height + self.spacing))
+    }
+}
+
+#[derive(Debug)]
+pub enum Layer {
+    /// One tile id per cell, row by row; 0 leaves a cell empty.
+    Tiles { name: String, visible: bool, ids: Vec<u32> },
+    Objects { name: String, objects: Vec<MapObject> },
+}
+
+/// A rectangle, ellipse or point placed on an object layer. Points have no
+/// width or height.
+#[derive(Debug)]
+pub struct MapObject {
+    pub name: String,
+    /// The class (called type in older versions of Tiled) the designer gave it.
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TileMap {
+    /// Reads the map at `path`, as TMX or JSON depending on its extension.
+    pub fn load(path: &Path) -> Result<TileMap, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        let map = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tmx") => TileMap::parse_tmx(&text),
+            Some("tmj" | "json") => TileMap::parse_json(&text),
+            _ => Err("unknown map format, expected a .tmx, .tmj or .json file".to_string()),
+        };
+        map.and_then(TileMap::validate).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse_tmx(text: &str) -> Result<TileMap, String> {
+        let doc = roxmltree::Document::parse(text).map_err(|err| err.to_string())?;
+        let map = doc.root_element();
+        if !map.has_tag_name("map") {
+            return Err("not a Tiled map".to_string());
+        }
+        check_layout(&attr_or(map, "orientation", "orthogonal".to_string())?, attr_or(map, "infinite", 0)? == 1)?;
+        let mut tilesets = Vec::new();
+        for node in map.children().filter(|node| node.has_tag_name("tileset")) {
+            tilesets.push(tmx_tileset(node)?);
+        }
+        let mut layers = Vec::new();
+        tmx_layers(map, &mut layers)?;
+        Ok(TileMap {
+            width: attr(map, "width")?,
+            height: attr(map, "height")?,
+            tile_width: attr(map, "tilewidth")?,
+            tile_height: attr(map, "tileheight")?,
+            tilesets,
+            layers,
+        })
+    }
+
+    pub fn parse_json(text: &str) -> Result<TileMap, String> {
+        let file: JsonMap = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        check_layout(&file.orientation, file.infinite)?;
+        let tilesets = file.tilesets.into_iter().map(JsonTileset::into_tileset).collect::<Result<_, _>>()?;
+        let mut layers = Vec::new();
+        json_layers(file.layers, &mut layers)?;
+        Ok(TileMap {
+            width: file.width,
+            height: file.height,
+            tile_width: file.tilewidth,
+            tile_height: file.tileheight,
+            tilesets,
+            layers,
+        })
+    }
+
+    fn validate(self) -> Result<TileMap, String> {
+        if self.width == 0 || self.height == 0 || self.tile_width == 0 || self.tile_height == 0 {
+            return Err("the map and its tiles must be at least 1 wide and high".to_string());
+        }
+        for tileset in &self.tilesets {
+            if tileset.columns == 0 || tileset.tile_width == 0 || tileset.tile_height == 0 {
+                return Err(format!("tileset `{}`: its tiles must be at least 1 wide and high", tileset.image));
+            }
+            let (x, y) = tileset.source(tileset.tile_count.saturating_sub(1));
+            if x + tileset.tile_width > tileset.image_width || y + tileset.tile_height > tileset.image_height {
+                return Err(format!("tileset `{}`: {} tiles don't fit the image", tileset.image, tileset.tile_count));
+            }
+        }
+        let cells = (self.width * self.height) as usize;
+        for layer in &self.layers {
+            if let Layer::Tiles { name, ids, .. } = layer {
+                if ids.len() != cells {
+                    return Err(format!("layer `{name}`: {} tiles for a {} by {} map", ids.len(), self.width, self.height));
+                }
+                if let Some(id) = ids.iter().find(|id| **id != 0 && self.tile(**id).is_none()) {
+                    return Err(format!("layer `{name}`: tile {id} is in none of the tilesets"));
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Index of the tileset tile `id` comes from and its index within that set.
+    pub fn tile(&self, id: u32) -> Option<(usize, u32)> {
+        let id = id & !FLIP_FLAGS;
+        let (set, tileset) = self.tilesets.iter()
+            .enumerate()
+            .filter(|(_, tileset)| tileset.first_id <= id)
+            .max_by_key(|(_, tileset)| tileset.first_id)?;
+        let index = id - tileset.first_id;
+        (index < tileset.tile_count).then_some((set, index))
+    }
+}
+
+fn check_layout(orientation: &str, infinite: bool) -> Result<(), String> {
+    if orientation != "orthogonal" {
+        return Err(format!("only orthogonal maps are supported, this one is {orientation}"));
+    }
+    if infinite {
+        return Err("infinite maps are not supported".to_string());
+    }
+    Ok(())
+}
+
+fn external_tileset(source: &str) -> String {
+    format!("tileset `{source}` is in a file of its own, embed it in the map")
+}
+
+fn unsupported_object(kind: &str, id: &str) -> String {
+    format!("object {id}: {kind} objects are not supported, use rectangles, ellipses or points")
+}
+
+fn attr<T: FromStr>(node: roxmltree::Node, name: &str) -> Result<T, String> {
+    let value = node.attribute(name)
+        .ok_or_else(|| format!("<{}> has no `{name}`", node.tag_name().name()))?;
+    value.parse().map_err(|_| format!("<{}> has an invalid `{name}`: {value}", node.tag_name().name()))
+}
+
+fn attr_or<T: FromStr>(node: roxmltree::Node, name: &str, default: T) -> Result<T, String> {
+    match node.attribute(name) {
+        Some(_) => attr(node, name),
+        None => Ok(default),
+    }
+}
+
+fn tmx_tileset(node: roxmltree::Node) -> Result<Tileset, String> {
+    if let Some(source) = node.attribute("source") {
+        return Err(external_tileset(source));
+    }
+    let name: String = attr_or(node, "name", String::new())?;
+    let image = node.children().find(|child| child.has_tag_name("image"))
+        .ok_or_else(|| format!("tileset `{name}` has no image, tilesets made of separate images are not supported"))?;
+    Ok(Tileset {
+        first_id: attr(node, "firstgid")?,
+        image: attr(image, "source")?,
+        image_width: attr(image, "width")?,
+        image_height: attr(image, "height")?,
+        tile_width: attr(node, "tilewidth")?,
+        tile_height: attr(node, "tileheight")?,
+        tile_count: attr(node, "tilecount")?,
+        columns: attr(node, "columns")?,
+        margin: attr_or(node, "margin", 0)?,
+        spacing: attr_or(node, "spacing", 0)?,
+    })
+}
+
+fn tmx_layers(parent: roxmltree::Node, layers: &mut Vec<Layer>) -> Result<(), String> {
+    for node in parent.children() {
+        match node.tag_name().name() {
+            "layer" => {
+                let name: String = attr_or(node, "name", String::new())?;
+                let data = node.children().find(|child| child.has_tag_name("data"))
+                    .ok_or_else(|| format!("layer `{name}` has no data"))?;
+                let ids = match data.attribute("encoding") {
+                    Some("csv") => parse_csv(data.text().unwrap_or("")).map_err(|err| format!("layer `{name}`: {err}"))?,
+                    Some(other) => return Err(format!("layer `{name}`: {other} tile data is not supported, save the map with CSV tile layers")),
+                    None => data.children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        .map(|tile| attr_or(tile, "gid", 0))
+                        .collect::<Result<_, _>>()?,
+                };
+                layers.push(Layer::Tiles { name, visible: attr_or(node, "visible", 1)? == 1, ids });
+            }
+            "objectgroup" => {
+                let mut objects = Vec::new();
+                for object in node.children().filter(|child| child.has_tag_name("object")) {
+                    let id = object.attribute("id").unwrap_or("?");
+                    if object.attribute("gid").is_some() {
+                        return Err(unsupported_object("tile", id));
+                    }
+                    if let Some(shape) = object.children().find(|child| ["polygon", "polyline", "text"].contains(&child.tag_name().name())) {
+                        return Err(unsupported_object(shape.tag_name().name(), id));
+                    }
+                    let class = object.attribute("type").or(object.attribute("class")).unwrap_or("");
+                    objects.push(MapObject {
+                        name: attr_or(object, "name", String::new())?,
+                        class: class.to_string(),
+                        x: attr(object, "x")?,
+                        y: attr(object, "y")?,
+                        width: attr_or(object, "width", 0f32)?,
+                        height: attr_or(object, "height", 0f32)?,
+                    });
+                }
+                layers.push(Layer::Objects { name: attr_or(node, "name", String::new())?, objects });
+            }
+            "group" => tmx_layers(node, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn parse_csv(text: &str) -> Result<Vec<u32>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(|_| format!("invalid tile id `{id}`")))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLayer {
+    TileLayer {
+        #[serde(default)]
+        name: String,
+        #[serde(default = "visible")]
+        visible: bool,
+        data: JsonData,
+    },
+    ObjectGroup {
+        #[serde(default)]
+        name: String,
+        objects: Vec<JsonObject>,
+    },
+    Group {
+        layers: Vec<JsonLayer>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+fn visible() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonData {
+    Ids(Vec<u32>),
+    Encoded(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    gid: Option<u32>,
+    polygon: Option<IgnoredAny>,
+    polyline: Option<IgnoredAny>,
+    text: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+}
+
+impl JsonTileset {
+    fn into_tileset(self) -> Result<Tileset, String> {
+        if let Some(source) = self.source {
+            return Err(external_tileset(&source));
+        }
+        let image = self.image
+            .ok_or_else(|| format!("tileset `{}` has no image, tilesets made of separate images are not supported", self.name))?;
+        Ok(Tileset {
+            first_id: self.firstgid,
+            image,
+            image_width: self.imagewidth,
+            image_height: self.imageheight,
+            tile_width: self.tilewidth,
+            tile_height: self.tileheight,
+            tile_count: self.tilecount,
+            columns: self.columns,
+            margin: self.margin,
+            spacing: self.spacing,
+        })
+    }
+}
+
+fn json_layers(json: Vec<JsonLayer>, layers: &mut Vec<Layer>) -> Result<(), String> {
+    for layer in json {
+        match layer {
+            JsonLayer::TileLayer { name, visible, data } => {
+                let ids = match data {
+                    JsonData::Ids(ids) => ids,
+                    JsonData::Encoded(_) => return Err(format!("layer `{name}`: encoded tile data is not supported, save the map with CSV tile layers")),
+                };
+                layers.push(Layer::Tiles { name, visible, ids });
+            }
+            JsonLayer::ObjectGroup { name, objects } => {
+                let mut parsed = Vec::new();
+                for object in objects {
+                    let id = object.id.to_string();
+                    if object.gid.is_some() {
+                        return Err(unsupported_object("tile", &id));
+                    }
+                    for (shape, present) in [("polygon", object.polygon.is_some()), ("polyline", object.polyline.is_some()), ("text", object.text.is_some())] {
+                        if present {
+                            return Err(unsupported_object(shape, &id));
+                        }
+                    }
+                    let class = if object.kind.is_empty() { object.class } else { object.kind };
+                    parsed.push(MapObject { name: object.name, class, x: object.x, y: object.y, width: object.width, height: object.height });
+                }
+                layers.push(Layer::Objects { name, objects: parsed });
+            }
+            JsonLayer::Group { layers: children } => json_layers(children, layers)?,
+            JsonLayer::Other => {}
+        }
+    }
+    Ok(())
+}
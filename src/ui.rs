@@ -0,0 +1,367 @@
+use ggez::{
+    event::MouseButton,
+    graphics::{self, Color, Rect},
+    glam::Vec2,
+    input::keyboard::KeyCode,
+    Context,
+    GameResult,
+};
+
+use crate::settings::{WIN_WIDTH, WIN_HEIGHT};
+
+/// Space between a panel's background and its content.
+const PADDING: f32 = 10f32;
+/// Space between the children of a panel.
+const SPACING: f32 = 8f32;
+/// Space between a button's frame and its text.
+const BUTTON_PADDING: f32 = 6f32;
+
+/// Refers to a widget of the `Ui` that created it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetId(usize);
+
+/// Where on the window a root panel is placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    TopCenter,
+    Center,
+    /// Spans the whole width of the window, along its bottom edge.
+    BottomBar,
+}
+
+/// How a panel lines up its children.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Column,
+    Row,
+}
+
+enum Kind {
+    Panel {
+        direction: Direction,
+        background: Option<Color>,
+        /// Whether children of a column are centered instead of left aligned.
+        centered: bool,
+        children: Vec<usize>,
+    },
+    Label { text: String, scale: f32, color: Color },
+    Button { text: String, scale: f32 },
+    ProgressBar { size: Vec2, value: f32, color: Color },
+}
+
+struct Widget {
+    kind: Kind,
+    parent: Option<usize>,
+    visible: bool,
+    rect: Rect,
+}
+
+/// A retained tree of widgets. Screens build it once, update texts and values
+/// as the game goes on, and hand it mouse and keyboard events; `draw` lays it
+/// out again every frame, so widgets may change size freely.
+#[derive(Default)]
+pub struct Ui {
+    widgets: Vec<Widget>,
+    roots: Vec<(Anchor, usize)>,
+    hovered: Option<usize>,
+    focused: Option<usize>,
+}
+
+impl Ui {
+    pub fn new() -> Ui {
+        Ui::default()
+    }
+
+    /// Adds a top level panel placed at `anchor`.
+    pub fn root(&mut self, anchor: Anchor, direction: Direction, background: Option<Color>) -> WidgetId {
+        let centered = anchor != Anchor::TopLeft;
+        let index = self.push(None, Kind::Panel { direction, background, centered, children: Vec::new() });
+        self.roots.push((anchor, index));
+        WidgetId(index)
+    }
+
+    pub fn panel(&mut self, parent: WidgetId, direction: Direction, background: Option<Color>) -> WidgetId {
+        let centered = match &self.widgets[parent.0].kind {
+            Kind::Panel { centered, .. } => *centered,
+            _ => false,
+        };
+        WidgetId(self.push(Some(parent.0), Kind::Panel { direction, background, centered, children: Vec::new() }))
+    }
+
+    pub fn label(&mut self, parent: WidgetId, text: impl Into<String>, scale: f32, color: Color) -> WidgetId {
+        WidgetId(self.push(Some(parent.0), Kind::Label { text: text.into(), scale, color }))
+    }
+
+    pub fn button(&mut self, parent: WidgetId, text: impl Into<String>, scale: f32) -> WidgetId {
+        WidgetId(self.push(Some(parent.0), Kind::Button { text: text.into(), scale }))
+    }
+
+    pub fn progress_bar(&mut self, parent: WidgetId, size: Vec2, color: Color) -> WidgetId {
+        WidgetId(self.push(Some(parent.0), Kind::ProgressBar { size, value: 0f32, color }))
+    }
+
+    fn push(&mut self, parent: Option<usize>, kind: Kind) -> usize {
+        let index = self.widgets.len();
+        self.widgets.push(Widget { kind, parent, visible: true, rect: Rect::default() });
+        if let Some(parent) = parent {
+            match &mut self.widgets[parent].kind {
+                Kind::Panel { children, .. } => children.push(index),
+                _ => panic!("only panels can have children"),
+            }
+        }
+        index
+    }
+
+    /// Changes the text of a label or button.
+    pub fn set_text(&mut self, id: WidgetId, new_text: impl Into<String>) {
+        match &mut self.widgets[id.0].kind {
+            Kind::Label { text, .. } | Kind::Button { text, .. } => *text = new_text.into(),
+            _ => (),
+        }
+    }
+
+    /// Changes the color of a label or progress bar.
+    pub fn set_color(&mut self, id: WidgetId, new_color: Color) {
+        match &mut self.widgets[id.0].kind {
+            Kind::Label { color, .. } | Kind::ProgressBar { color, .. } => *color = new_color,
+            _ => (),
+        }
+    }
+
+    /// Sets how full a progress bar is, from 0 to 1.
+    pub fn set_progress(&mut self, id: WidgetId, new_value: f32) {
+        if let Kind::ProgressBar { value, .. } = &mut self.widgets[id.0].kind {
+            *value = new_value.clamp(0f32, 1f32);
+        }
+    }
+
+    /// Hidden widgets take no space and ignore input, along with their children.
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        self.widgets[id.0].visible = visible;
+    }
+
+    fn is_shown(&self, mut index: usize) -> bool {
+        loop {
+            let widget = &self.widgets[index];
+            if !widget.visible {
+                return false;
+            }
+            match widget.parent {
+                Some(parent) => index = parent,
+                None => return true,
+            }
+        }
+    }
+
+    fn buttons(&self) -> Vec<usize> {
+        (0..self.widgets.len())
+            .filter(|index| matches!(self.widgets[*index].kind, Kind::Button { .. }) && self.is_shown(*index))
+            .collect()
+    }
+
+    fn button_at(&self, pos: Vec2) -> Option<usize> {
+        self.buttons().into_iter().find(|index| self.widgets[*index].rect.contains(pos))
+    }
+
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused.map(WidgetId)
+    }
+
+    pub fn set_focus(&mut self, id: WidgetId) {
+        self.focused = Some(id.0);
+    }
+
+    /// Moves the keyboard focus `steps` buttons forward (or backward when
+    /// negative), wrapping around.
+    pub fn move_focus(&mut self, steps: isize) {
+        let buttons = self.buttons();
+        if buttons.is_empty() {
+            return;
+        }
+        let count = buttons.len() as isize;
+        let next = match self.focused.and_then(|focused| buttons.iter().position(|index| *index == focused)) {
+            Some(current) => (current as isize + steps).rem_euclid(count),
+            None if steps < 0 => count - 1,
+            None => 0,
+        };
+        self.focused = Some(buttons[next as usize]);
+    }
+
+    /// Hovering a button also gives it the keyboard focus.
+    pub fn mouse_motion(&mut self, pos: Vec2) {
+        self.hovered = self.button_at(pos);
+        if self.hovered.is_some() {
+            self.focused = self.hovered;
+        }
+    }
+
+    /// Returns the button that was clicked, if any.
+    pub fn mouse_down(&mut self, button: MouseButton, pos: Vec2) -> Option<WidgetId> {
+        if button != MouseButton::Left {
+            return None;
+        }
+        let clicked = self.button_at(pos)?;
+        self.focused = Some(clicked);
+        Some(WidgetId(clicked))
+    }
+
+    /// Arrow keys and Tab move the focus; returns the focused button when Enter is pressed.
+    pub fn key_down(&mut self, key: KeyCode) -> Option<WidgetId> {
+        match key {
+            KeyCode::Down | KeyCode::Right | KeyCode::Tab => self.move_focus(1),
+            KeyCode::Up | KeyCode::Left => self.move_focus(-1),
+            KeyCode::Return | KeyCode::NumpadEnter => return self.focused.filter(|index| self.is_shown(*index)).map(WidgetId),
+            _ => (),
+        }
+        None
+    }
+
+    fn measure(&self, ctx: &Context, index: usize) -> GameResult<Vec2> {
+        let widget = &self.widgets[index];
+        if !widget.visible {
+            return Ok(Vec2::ZERO);
+        }
+        let size = match &widget.kind {
+            Kind::Label { text, scale, .. } => text_size(ctx, text, *scale)?,
+            Kind::Button { text, scale } => text_size(ctx, text, *scale)? + Vec2::splat(2f32 * BUTTON_PADDING),
+            Kind::ProgressBar { size, .. } => *size,
+            Kind::Panel { direction, background, children, .. } => {
+                let mut size = Vec2::ZERO;
+                let mut shown = 0;
+                for child in children {
+                    if !self.widgets[*child].visible {
+                        continue;
+                    }
+                    let child_size = self.measure(ctx, *child)?;
+                    match direction {
+                        Direction::Column => size = Vec2::new(size.x.max(child_size.x), size.y + child_size.y),
+                        Direction::Row => size = Vec2::new(size.x + child_size.x, size.y.max(child_size.y)),
+                    }
+                    shown += 1;
+                }
+                let gaps = SPACING * (shown as f32 - 1f32).max(0f32);
+                match direction {
+                    Direction::Column => size.y += gaps,
+                    Direction::Row => size.x += gaps,
+                }
+                if background.is_some() {
+                    size += Vec2::splat(2f32 * PADDING);
+                }
+                size
+            }
+        };
+        Ok(size)
+    }
+
+    /// Gives the widget and its children their rects; `area` is the space the
+    /// parent reserved for it.
+    fn place(&mut self, ctx: &Context, index: usize, area: Rect) -> GameResult {
+        self.widgets[index].rect = area;
+        let (direction, padding, centered, children) = match &self.widgets[index].kind {
+            Kind::Panel { direction, background, centered, children } =>
+                (*direction, if background.is_some() { PADDING } else { 0f32 }, *centered, children.clone()),
+            _ => return Ok(()),
+        };
+        let content = self.measure(ctx, index)? - Vec2::splat(2f32 * padding);
+        let mut pos = Vec2::new(area.x + padding, area.y + padding);
+        //rows are always centered in the space they got, which matters for bars
+        if direction == Direction::Row {
+            pos.x += (area.w - 2f32 * padding - content.x).max(0f32) / 2f32;
+        }
+        for child in children {
+            if !self.widgets[child].visible {
+                continue;
+            }
+            let size = self.measure(ctx, child)?;
+            match direction {
+                Direction::Column => {
+                    let x = if centered { area.x + (area.w - size.x) / 2f32 } else { pos.x };
+                    self.place(ctx, child, Rect::new(x, pos.y, size.x, size.y))?;
+                    pos.y += size.y + SPACING;
+                }
+                Direction::Row => {
+                    self.place(ctx, child, Rect::new(pos.x, pos.y, size.x, content.y))?;
+                    pos.x += size.x + SPACING;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn layout(&mut self, ctx: &Context) -> GameResult {
+        for (anchor, index) in self.roots.clone() {
+            if !self.widgets[index].visible {
+                continue;
+            }
+            let size = self.measure(ctx, index)?;
+            let area = match anchor {
+                Anchor::TopLeft => Rect::new(0f32, 0f32, size.x, size.y),
+                Anchor::TopCenter => Rect::new((WIN_WIDTH - size.x) / 2f32, PADDING, size.x, size.y),
+                Anchor::Center => Rect::new((WIN_WIDTH - size.x) / 2f32, (WIN_HEIGHT - size.y) / 2f32, size.x, size.y),
+                Anchor::BottomBar => Rect::new(0f32, WIN_HEIGHT - size.y, WIN_WIDTH, size.y),
+            };
+            self.place(ctx, index, area)?;
+        }
+        Ok(())
+    }
+
+    /// Lays the widgets out and draws every visible one.
+    pub fn draw(&mut self, ctx: &Context, canvas: &mut graphics::Canvas) -> GameResult {
+        self.layout(ctx)?;
+        for (_, index) in &self.roots {
+            self.draw_widget(canvas, *index);
+        }
+        Ok(())
+    }
+
+    fn draw_widget(&self, canvas: &mut graphics::Canvas, index: usize) {
+        let widget = &self.widgets[index];
+        if !widget.visible {
+            return;
+        }
+        let rect = widget.rect;
+        match &widget.kind {
+            Kind::Panel { background, children, .. } => {
+                if let Some(background) = background {
+                    canvas.draw(&graphics::Quad, graphics::DrawParam::default().dest_rect(rect).color(*background));
+                }
+                for child in children {
+                    self.draw_widget(canvas, *child);
+                }
+            }
+            Kind::Label { text, scale, color } => {
+                let mut text = graphics::Text::new(text.as_str());
+                text.set_scale(*scale);
+                canvas.draw(&text, graphics::DrawParam::default().dest(rect.point()).color(*color));
+            }
+            Kind::Button { text, scale } => {
+                let active = self.focused == Some(index) || self.hovered == Some(index);
+                let (frame, text_color) = if active {
+                    (Color::new(1f32, 1f32, 1f32, 0.2), Color::YELLOW)
+                }
+                else {
+                    (Color::new(1f32, 1f32, 1f32, 0.05), Color::from_rgb(120, 150, 130))
+                };
+                canvas.draw(&graphics::Quad, graphics::DrawParam::default().dest_rect(rect).color(frame));
+                let mut text = graphics::Text::new(text.as_str());
+                text.set_scale(*scale);
+                canvas.draw(&text, graphics::DrawParam::default()
+                    .dest(Vec2::new(rect.x + BUTTON_PADDING, rect.y + BUTTON_PADDING))
+                    .color(text_color));
+            }
+            Kind::ProgressBar { value, color, .. } => {
+                canvas.draw(&graphics::Quad, graphics::DrawParam::default().dest_rect(rect).color(Color::new(0f32, 0f32, 0f32, 0.6)));
+                let filled = Rect::new(rect.x, rect.y, rect.w * value, rect.h);
+                canvas.draw(&graphics::Quad, graphics::DrawParam::default().dest_rect(filled).color(*color));
+            }
+        }
+    }
+}
+
+fn text_size(ctx: &Context, text: &str, scale: f32) -> GameResult<Vec2> {
+    let mut text = graphics::Text::new(text);
+    text.set_scale(scale);
+    let size = text.measure(ctx)?;
+    Ok(Vec2::new(size.x, size.y))
+}
@@ -0,0 +1,74 @@
+use ggez::glam::Vec2;
+
+use crate::physics::{sweep, Aabb};
+use crate::settings::PI;
+
+/// Angle between two rays where no corner or cone edge asks for one.
+const RAY_STEP: f32 = PI / 180f32;
+
+/// How far beside a corner the extra rays pass it, so the shadow behind it
+/// starts right at the corner.
+const CORNER_NUDGE: f32 = 0.0005;
+
+/// What the player can see: a cone towards where they aim plus a small circle
+/// around them, with solids casting shadows over both.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sight {
+    pub origin: Vec2,
+    /// Direction the cone points in, as an angle from the x axis.
+    pub facing: f32,
+    /// Half the width of the cone.
+    pub half_angle: f32,
+    /// Length of the cone.
+    pub range: f32,
+    /// Radius seen all around.
+    pub ambient: f32,
+}
+
+impl Sight {
+    /// How far the sight reaches in direction `angle` when nothing is in the way.
+    pub fn reach(&self, angle: f32) -> f32 {
+        if wrap(angle - self.facing).abs() <= self.half_angle { self.range.max(self.ambient) } else { self.ambient }
+    }
+
+    /// Whether `point` is in sight and no solid stands between it and the origin.
+    pub fn sees(&self, point: Vec2, solids: &[Aabb]) -> bool {
+        let offset = point - self.origin;
+        offset.length() <= self.reach(offset.y.atan2(offset.x))
+            && sweep(self.origin, Vec2::ZERO, offset, solids).is_none()
+    }
+
+    /// Outline of the area in sight, in order around the origin. Rays go out at
+    /// a steady step and past both sides of every corner of the solids in reach,
+    /// so shadows have sharp edges.
+    pub fn outline(&self, solids: &[Aabb]) -> Vec<Vec2> {
+        let mut angles: Vec<f32> = (0..(2f32 * PI / RAY_STEP) as u32).map(|step| step as f32 * RAY_STEP).collect();
+        for edge in [self.facing - self.half_angle, self.facing + self.half_angle] {
+            angles.extend([edge - CORNER_NUDGE, edge + CORNER_NUDGE]);
+        }
+        let furthest = self.range.max(self.ambient);
+        for solid in solids {
+            for corner in [solid.min, solid.max, Vec2::new(solid.min.x, solid.max.y), Vec2::new(solid.max.x, solid.min.y)] {
+                let offset = corner - self.origin;
+                if offset.length() <= furthest {
+                    let angle = offset.y.atan2(offset.x);
+                    angles.extend([angle - CORNER_NUDGE, angle + CORNER_NUDGE]);
+                }
+            }
+        }
+        let mut angles: Vec<f32> = angles.into_iter().map(wrap).collect();
+        angles.sort_by(f32::total_cmp);
+        angles.into_iter()
+            .map(|angle| {
+                let ray = Vec2::new(angle.cos(), angle.sin()) * self.reach(angle);
+                let fraction = sweep(self.origin, Vec2::ZERO, ray, solids).map_or(1f32, |(fraction, _)| fraction);
+                self.origin + ray * fraction
+            })
+            .collect()
+    }
+}
+
+/// `angle` brought into -PI..PI.
+fn wrap(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2f32 * PI) - PI
+}
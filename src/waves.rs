@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::config::check;
+use crate::enemies::{EnemyKinds, EnemyKindId, MAX_SPEED, MAX_HEALTH};
+use crate::level::Level;
+
+/// File in the resources directory that defines the waves of a round.
+pub const WAVES_FILE: &str = "waves.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WavesFile {
+    start_delay: u16,
+    intermission: u16,
+    scaling: Scaling,
+    wave: Vec<WaveDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WaveDef {
+    count: u16,
+    interval: u16,
+    edges: Vec<String>,
+    enemies: BTreeMap<String, u32>,
+}
+
+/// Factors applied once more for every wave past the last one in the file.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scaling {
+    pub count: f32,
+    pub interval: f32,
+    pub health: f32,
+    pub speed: f32,
+}
+
+#[derive(Debug)]
+struct Wave {
+    count: u16,
+    interval: u16,
+    edges: Vec<usize>,
+    enemies: Vec<(EnemyKindId, u32)>,
+}
+
+/// What wave number `n` is made of, after scaling.
+#[derive(Debug)]
+pub struct WavePlan<'a> {
+    /// Enemies spawned in the wave.
+    pub count: u16,
+    /// Ticks between two spawns.
+    pub interval: u16,
+    /// Indices of the spawn areas of the level enemies walk in from.
+    pub edges: &'a [usize],
+    /// Kinds the wave is made of, with how often each is picked.
+    pub enemies: &'a [(EnemyKindId, u32)],
+    /// Multiplies the health of every enemy in the wave.
+    pub health: f32,
+    /// Multiplies the speed of every enemy in the wave.
+    pub speed: f32,
+}
+
+/// The waves of a round, as defined in the data file.
+#[derive(Debug)]
+pub struct Waves {
+    /// Ticks from the start of a round to the first wave.
+    pub start_delay: u16,
+    /// Ticks between clearing a wave and the next one, while the shop is open.
+    pub intermission: u16,
+    scaling: Scaling,
+    waves: Vec<Wave>,
+}
+
+impl Waves {
+    pub fn load(resources_dir: &Path, enemies: &EnemyKinds, level: &Level) -> Result<Waves, String> {
+        let path = resources_dir.join(WAVES_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        Waves::parse(&text, enemies, level).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str, enemies: &EnemyKinds, level: &Level) -> Result<Waves, String> {
+        let file: WavesFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        if file.wave.is_empty() {
+            return Err("no waves defined".to_string());
+        }
+        check("start_delay", file.start_delay, 1, 6000)?;
+        check("intermission", file.intermission, 1, 6000)?;
+        check("scaling.count", file.scaling.count, 1f32, 10f32)?;
+        check("scaling.interval", file.scaling.interval, 0.1, 1f32)?;
+        check("scaling.health", file.scaling.health, 1f32, 10f32)?;
+        check("scaling.speed", file.scaling.speed, 1f32, 10f32)?;
+
+        let mut waves = Vec::new();
+        for (index, def) in file.wave.into_iter().enumerate() {
+            let in_wave = |err: String| format!("wave {}: {err}", index + 1);
+            check("count", def.count, 1, 1000).map_err(in_wave)?;
+            check("interval", def.interval, 1, 6000).map_err(in_wave)?;
+            if def.edges.is_empty() {
+                return Err(in_wave("no edges to spawn from".to_string()));
+            }
+            let mut edges = Vec::new();
+            for name in &def.edges {
+                let known = || level.spawns.iter().map(|spawn| spawn.name.as_str()).collect::<Vec<_>>().join(", ");
+                edges.push(level.spawn_id(name).ok_or_else(|| in_wave(format!("the map has no spawn `{name}`, only {}", known())))?);
+            }
+            let mut kinds = Vec::new();
+            for (name, weight) in def.enemies {
+                let id = enemies.id(&name).ok_or_else(|| in_wave(format!("unknown enemy `{name}`")))?;
+                check(&format!("enemies.{name}"), weight, 1, 10000).map_err(in_wave)?;
+                kinds.push((id, weight));
+            }
+            if kinds.is_empty() {
+                return Err(in_wave("no enemies".to_string()));
+            }
+            waves.push(Wave { count: def.count, interval: def.interval, edges, enemies: kinds });
+        }
+        Ok(Waves { start_delay: file.start_delay, intermission: file.intermission, scaling: file.scaling, waves })
+    }
+
+    /// Wave `number`, counting from 1. Waves past the last one in the file repeat
+    /// it, scaled up once per wave.
+    pub fn plan(&self, number: u32) -> WavePlan<'_> {
+        let last = self.waves.len() as u32;
+        let wave = &self.waves[(number.clamp(1, last) - 1) as usize];
+        let extra = number.saturating_sub(last) as i32;
+        let scale = |factor: f32| factor.powi(extra);
+        WavePlan {
+            count: (wave.count as f32 * scale(self.scaling.count)).round().min(1000f32) as u16,
+            interval: (wave.interval as f32 * scale(self.scaling.interval)).round().max(1f32) as u16,
+            edges: &wave.edges,
+            enemies: &wave.enemies,
+            //capped so the factors stay finite however long a run lasts, the
+            //scaled values are clamped again when an enemy is added
+            health: scale(self.scaling.health).min(MAX_HEALTH as f32),
+            speed: scale(self.scaling.speed).min(MAX_SPEED),
+        }
+    }
+}
+
+/// Where the round is in its current wave.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Waiting for the wave to start; the shop is open.
+    Intermission { ticks_left: u16 },
+    /// Enemies of the wave are still coming.
+    Spawning { left: u16, next_in: u16 },
+    /// Everything spawned; waiting for the player to kill the rest.
+    Clearing,
+}
+
+/// What the director wants the world to do this tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirectorStep {
+    Idle,
+    Started,
+    Spawn,
+    Cleared,
+}
+
+/// Sends the waves one after the other.
+#[derive(Debug, Clone)]
+pub struct Director {
+    /// The wave being fought, or the next one during an intermission.
+    pub wave: u32,
+    pub phase: Phase,
+}
+
+impl Director {
+    pub fn new(waves: &Waves) -> Director {
+        Director { wave: 1, phase: Phase::Intermission { ticks_left: waves.start_delay } }
+    }
+
+    /// `interval_scale` stretches or shortens the time between two spawns.
+    pub fn step(&mut self, waves: &Waves, enemies_alive: usize, interval_scale: f32) -> DirectorStep {
+        match &mut self.phase {
+            Phase::Intermission { ticks_left } => {
+                *ticks_left -= 1;
+                if *ticks_left == 0 {
+                    self.phase = Phase::Spawning { left: waves.plan(self.wave).count, next_in: 0 };
+                    return DirectorStep::Started;
+                }
+                DirectorStep::Idle
+            }
+            Phase::Spawning { left, next_in } => {
+                if *next_in > 0 {
+                    *next_in -= 1;
+                    return DirectorStep::Idle;
+                }
+                *left -= 1;
+                let interval = (waves.plan(self.wave).interval as f32 * interval_scale).round().max(1f32) as u16;
+                *next_in = interval - 1;
+                if *left == 0 {
+                    self.phase = Phase::Clearing;
+                }
+                DirectorStep::Spawn
+            }
+            Phase::Clearing => {
+                if enemies_alive > 0 {
+                    return DirectorStep::Idle;
+                }
+                self.wave += 1;
+                self.phase = Phase::Intermission { ticks_left: waves.intermission };
+                DirectorStep::Cleared
+            }
+        }
+    }
+
+    pub fn in_intermission(&self) -> bool {
+        matches!(self.phase, Phase::Intermission { .. })
+    }
+}
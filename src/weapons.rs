@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::assets::{Manifest, ImageHandle, SoundHandle};
+use crate::config::check;
+use crate::settings::PI;
+use crate::input::SLOT_KEYS;
+
+/// File in the resources directory that defines every weapon.
+pub const WEAPONS_FILE: &str = "weapons.toml";
+/// Longest a weapon may take between two shots, in ticks.
+pub const MAX_FIRE_INTERVAL: u16 = 600;
+/// Longest a weapon may take to reload, in ticks.
+pub const MAX_RELOAD_TIME: u16 = 600;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WeaponsFile {
+    weapon: Vec<WeaponDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WeaponDef {
+    name: String,
+    price: u16,
+    fire_interval: u16,
+    projectiles: u16,
+    spread: f32,
+    bullet_speed: f32,
+    lifetime: u16,
+    damage: u16,
+    knockback: f32,
+    magazine: u16,
+    reserve_ammo: Option<u16>,
+    reload_time: u16,
+    sprite: String,
+    sound: String,
+    empty_sound: String,
+}
+
+#[derive(Debug)]
+pub struct Weapon {
+    pub name: String,
+    /// Dollars it costs in the pause menu; free weapons are owned from the start.
+    pub price: u16,
+    /// Ticks between two shots.
+    pub fire_interval: u16,
+    /// Bullets fired per shot.
+    pub projectiles: u16,
+    /// Width of the cone the bullets are spread over, in radians.
+    pub spread: f32,
+    pub bullet_speed: f32,
+    /// Ticks a bullet flies before it disappears.
+    pub lifetime: u16,
+    pub damage: u16,
+    /// Pixels an enemy is pushed back along the bullet's path when hit.
+    pub knockback: f32,
+    /// Shots before the weapon has to be reloaded.
+    pub magazine: u16,
+    /// Rounds a round starts with besides the loaded magazine; `None` is unlimited.
+    pub reserve_ammo: Option<u16>,
+    /// Ticks a reload takes.
+    pub reload_time: u16,
+    pub sprite: ImageHandle,
+    pub sound: SoundHandle,
+    /// Played when trying to fire with an empty magazine.
+    pub empty_sound: SoundHandle,
+}
+
+/// Every weapon the game knows, in the order of the keys that pick them.
+#[derive(Debug)]
+pub struct Weapons {
+    weapons: Vec<Weapon>,
+}
+
+impl Weapons {
+    pub fn load(resources_dir: &Path, manifest: &mut Manifest) -> Result<Weapons, String> {
+        let path = resources_dir.join(WEAPONS_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        Weapons::parse(&text, manifest).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str, manifest: &mut Manifest) -> Result<Weapons, String> {
+        let file: WeaponsFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        if file.weapon.is_empty() {
+            return Err("no weapons defined".to_string());
+        }
+        //weapons are picked with the number keys
+        if file.weapon.len() > SLOT_KEYS.len() {
+            return Err(format!("{} weapons defined, at most {} are supported", file.weapon.len(), SLOT_KEYS.len()));
+        }
+        if file.weapon[0].price != 0 {
+            return Err(format!("weapon `{}` is the starting weapon and must have a price of 0", file.weapon[0].name));
+        }
+        let mut weapons: Vec<Weapon> = Vec::new();
+        for def in file.weapon {
+            if weapons.iter().any(|weapon| weapon.name == def.name) {
+                return Err(format!("weapon `{}` is defined twice", def.name));
+            }
+            let in_weapon = |err: String| format!("weapon `{}`: {err}", def.name);
+            check("fire_interval", def.fire_interval, 1, MAX_FIRE_INTERVAL).map_err(in_weapon)?;
+            check("projectiles", def.projectiles, 1, 100).map_err(in_weapon)?;
+            check("spread", def.spread, 0f32, 2f32 * PI).map_err(in_weapon)?;
+            check("bullet_speed", def.bullet_speed, 1f32, 200f32).map_err(in_weapon)?;
+            check("lifetime", def.lifetime, 1, 600).map_err(in_weapon)?;
+            check("damage", def.damage, 1, 1000).map_err(in_weapon)?;
+            check("knockback", def.knockback, 0f32, 200f32).map_err(in_weapon)?;
+            check("magazine", def.magazine, 1, 1000).map_err(in_weapon)?;
+            check("reload_time", def.reload_time, 1, MAX_RELOAD_TIME).map_err(in_weapon)?;
+            weapons.push(Weapon {
+                price: def.price,
+                fire_interval: def.fire_interval,
+                projectiles: def.projectiles,
+                spread: def.spread,
+                bullet_speed: def.bullet_speed,
+                lifetime: def.lifetime,
+                damage: def.damage,
+                knockback: def.knockback,
+                magazine: def.magazine,
+                reserve_ammo: def.reserve_ammo,
+                reload_time: def.reload_time,
+                sprite: manifest.image(&def.sprite),
+                sound: manifest.sound(&def.sound),
+                empty_sound: manifest.sound(&def.empty_sound),
+                name: def.name,
+            });
+        }
+        Ok(Weapons { weapons })
+    }
+
+    pub fn get(&self, slot: usize) -> &Weapon {
+        &self.weapons[slot]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Weapon> {
+        self.weapons.iter()
+    }
+}
@@ -0,0 +1,1015 @@
+use std::rc::Rc;
+
+use ggez::glam::Vec2;
+
+use cgmath::Point2;
+
+use collision::{
+    Aabb2,
+    dbvt::DynamicBoundingVolumeTree,
+    dbvt::TreeValue,
+    dbvt::DiscreteVisitor,
+};
+
+use rand::{Rng, SeedableRng};
+
+use rand_chacha::ChaCha8Rng;
+
+use enum_map::EnumMap;
+
+use libm::{atan2f, sqrt};
+
+use crate::settings::{TICKS_PER_SECOND, WIN_WIDTH, WIN_HEIGHT, PI, HIT_FLASH_TIME, PLAYER_HEIGHT, BULLET_HEIGHT, PLAYER_WIDTH, PLAYER_SOLID_HALF, ENEMY_WIDTH, BULLET_WIDTH,
+    PICKUP_RADIUS, SPEED_BOOST, PIERCE_GRACE};
+use crate::config::Config;
+use crate::assets::{ImageHandle, SoundHandle};
+use crate::animation::{Animation, AnimationEvent};
+use crate::data::GameData;
+use crate::enemies::{Behaviour, EnemyKindId, MAX_SPEED, MAX_HEALTH};
+use crate::pickups::{Effect, Pickup, PickupKindId, PowerUp};
+use crate::waves::{Director, DirectorStep};
+use crate::difficulty::{AdaptiveDifficulty, Performance};
+use crate::steering;
+use crate::physics::{Aabb, sweep, move_and_slide};
+use crate::camera::Camera;
+use crate::vision::Sight;
+
+/// Name of the animation events that are footsteps, heard even out of sight.
+const FOOTSTEP: &str = "footstep";
+
+pub fn vec_from_angle(angle: f32) -> Vec2 {
+    let vx = angle.sin();
+    let vy = angle.cos();
+    Vec2::new(vx, vy)
+}
+
+pub fn distance(e1: &Entity, e2: &Entity) -> f32{
+    let dist = e1.pos - e2.pos;
+    sqrt((dist.x * dist.x + dist.y * dist.y) as f64) as f32
+}
+
+#[derive(Debug, Clone)]
+pub enum EntityTypes{
+    Player,
+    Bullet,
+    Enemy,
+    Particle,
+    /// Shot by spitters at the player.
+    Spit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State{
+    Playing,
+    Paused,
+    Unpausing,
+    GameOver,
+}
+
+/// Rule set a round is played with, picked in the mode select screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode{
+    Classic,
+    /// Less health and no starting money.
+    Hardcore,
+}
+
+impl GameMode{
+    pub const ALL: [GameMode; 2] = [GameMode::Classic, GameMode::Hardcore];
+
+    /// Name used in replay files and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Hardcore => "hardcore",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GameMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+/// Rounds the player has for one weapon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ammo{
+    pub magazine: u16,
+    /// `None` for weapons with unlimited spare rounds.
+    pub reserve: Option<u16>,
+}
+
+/// How a round went, shown on the death screen.
+#[derive(Debug, Clone, Default)]
+pub struct Stats{
+    pub ticks_survived: u64,
+    pub kills: u32,
+    /// Bullets that hit an enemy, whether they killed it or not.
+    pub hits: u32,
+    /// Bullets fired.
+    pub shots: u32,
+    pub dollars_earned: u32,
+    pub waves_cleared: u32,
+    pub pickups: u32,
+}
+
+/// Things that happened during a tick which the renderer may want to react to
+/// (sounds, effects). The simulation never plays or draws anything itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldEvent{
+    /// A weapon was fired; carries the weapon's sound and where its muzzle was.
+    ShotFired(SoundHandle, Vec2),
+    /// The trigger was pulled on an empty magazine.
+    EmptyClick(SoundHandle),
+    /// Wave number `n` started spawning.
+    WaveStarted(u32),
+    /// Wave number `n` was cleared and the shop opened.
+    WaveCleared(u32),
+    /// The footsteps of the nearest enemy that can be heard, at a loudness from
+    /// 0 to 1 that falls off with its distance.
+    Footstep { sound: SoundHandle, loudness: f32 },
+    /// An enemy blew up, hurting everything within `radius` of `pos`.
+    Exploded { pos: Vec2, radius: f32 },
+    /// The player picked something up; carries its sound.
+    PickedUp(SoundHandle),
+    Animation(AnimationEvent),
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct Entity{
+    pub entity_type: EntityTypes,
+    pub pos: Vec2,
+    pub d: Vec2,
+    pub health: u16,
+    /// Health a bullet takes from an enemy, or an enemy from the player.
+    pub damage: u16,
+    /// How far a bullet pushes back the enemy it hits.
+    pub knockback: f32,
+    /// Pixels per tick an enemy walks at.
+    pub speed: f32,
+    /// Ticks left of the tint shown after being hit.
+    pub flash: u16,
+    /// Ticks until an enemy may use its behaviour again, e.g. spit, or until a
+    /// piercing bullet may hurt again.
+    pub cooldown: u16,
+    pub rotation: f32,
+    pub sprite: ImageHandle,
+    pub animation: Option<Animation>,
+    /// Set for enemies only.
+    pub kind: Option<EnemyKindId>,
+}
+
+impl Entity{
+    /// An entity standing still at `pos`, with every other field zeroed.
+    pub fn new(entity_type: EntityTypes, pos: Vec2, sprite: ImageHandle) -> Entity {
+        Entity {
+            entity_type,
+            pos,
+            d: Vec2::ZERO,
+            health: 0,
+            damage: 0,
+            knockback: 0f32,
+            speed: 0f32,
+            flash: 0,
+            cooldown: 0,
+            rotation: 0f32,
+            sprite,
+            animation: None,
+            kind: None,
+        }
+    }
+}
+
+/// Snapshot of the player's intent for a single tick.
+#[derive(Debug, Clone, Default)]
+pub struct Input{
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub fire: bool,
+    pub aim: Vec2,
+    pub toggle_pause: bool,
+    pub reload: bool,
+    /// Slot in the weapons file of the gun to buy or switch to.
+    pub select_gun: Option<usize>,
+}
+
+impl Input{
+    /// The input a round starts with, before any event arrived.
+    pub fn new() -> Input {
+        Input {
+            aim: Vec2::new(WIN_WIDTH / 2f32, WIN_WIDTH),
+            ..Input::default()
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        self.up || self.down || self.left || self.right
+    }
+}
+
+/// The whole gameplay simulation. Knows nothing about windows, images or sounds,
+/// so it can be stepped headlessly.
+pub struct World {
+    pub player: Entity,
+    pub particles: Vec<Entity>,
+    pub bullets: Vec<Entity>,
+    pub enemies: Vec<Entity>,
+    pub spit: Vec<Entity>,
+    pub pickups: Vec<Pickup>,
+    /// Ticks left of every power-up; 0 when it isn't active.
+    pub power_ups: EnumMap<PowerUp, u16>,
+    pub reloading: u16,
+    pub state: State,
+    pub dollars: u16,
+    /// Which of the weapons in the data files the player owns, by slot.
+    pub owned_guns: Vec<bool>,
+    pub using_gun: usize,
+    /// Ammo of every weapon, by slot.
+    pub ammo: Vec<Ammo>,
+    /// Ticks until the reload in progress is done; 0 when not reloading.
+    pub reload_left: u16,
+    pub tick: u64,
+    pub director: Director,
+    pub difficulty: AdaptiveDifficulty,
+    pub camera: Camera,
+    pub stats: Stats,
+    /// Health the player started the round with.
+    pub max_health: u16,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub config: Config,
+    pub data: Rc<GameData>,
+    rng: ChaCha8Rng,
+    events: Vec<WorldEvent>,
+}
+
+impl World{
+
+    /// Creates a fresh round. Every random decision is drawn from an RNG seeded
+    /// with `seed`, so the same seed, mode, config and inputs always play out the same way.
+    pub fn new(seed: u64, mode: GameMode, config: Config, data: Rc<GameData>) -> World {
+        let (health, dollars) = match mode {
+            GameMode::Classic => (100, 199),
+            GameMode::Hardcore => (25, 0),
+        };
+        let idle = data.core_clips.player_idle;
+        let player = Entity {
+            health,
+            animation: Some(Animation::new(idle, &data.clips)),
+            ..Entity::new(EntityTypes::Player, data.level.player_start, data.clips.get(idle).image)
+        };
+        let camera = Camera::new(player.pos, Vec2::new(WIN_WIDTH, WIN_HEIGHT), data.level.size);
+
+        let owned_guns = data.weapons.iter().map(|weapon| weapon.price == 0).collect();
+        let ammo = data.weapons.iter()
+            .map(|weapon| Ammo { magazine: weapon.magazine, reserve: weapon.reserve_ammo })
+            .collect();
+        let director = Director::new(&data.waves);
+
+        let mut world = World {
+            player,
+            particles: Vec::new(),
+            bullets: Vec::new(),
+            enemies: Vec::new(),
+            spit: Vec::new(),
+            pickups: Vec::new(),
+            power_ups: EnumMap::default(),
+            reloading: 0,
+            state: State::Playing,
+            dollars,
+            owned_guns,
+            using_gun: 0,
+            ammo,
+            reload_left: 0,
+            tick: 0,
+            director,
+            difficulty: AdaptiveDifficulty::new(),
+            camera,
+            stats: Stats::default(),
+            max_health: health,
+            seed,
+            mode,
+            config,
+            data,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            events: Vec::new(),
+        };
+        world.restock();
+        world
+    }
+
+    /// Advances the simulation by exactly one tick.
+    pub fn step(&mut self, input: &Input) {
+        if input.toggle_pause {
+            match self.state {
+                State::Playing => self.state = State::Paused,
+                State::Paused => self.state = State::Unpausing,
+                State::Unpausing => self.state = State::Paused,
+                State::GameOver => (),
+            }
+        }
+        if let Some(gun) = input.select_gun {
+            if self.shop_open() {
+                self.select_gun(gun);
+            }
+        }
+
+        match self.state{
+            State::Playing => {
+
+                self.advance_frames(EntityTypes::Player, input);
+                self.advance_frames(EntityTypes::Enemy, input);
+
+                let data = Rc::clone(&self.data);
+                let solids = &data.level.solids;
+
+                //the edges of the arena keep the player in like walls do
+                let movement = |pressed: bool| if pressed { self.config.px_movement } else { 0f32 };
+                let boost = if self.power_ups[PowerUp::Speed] > 0 { SPEED_BOOST } else { 1f32 };
+                let delta = Vec2::new(movement(input.right) - movement(input.left), movement(input.down) - movement(input.up)) * boost;
+                self.player.pos = move_and_slide(self.player.pos, Vec2::splat(PLAYER_SOLID_HALF), delta, &data.level.enclosed).0;
+
+                self.camera.follow(self.player.pos, self.config.camera_smoothing);
+
+                //rotate player towards cursor
+                let aim = self.camera.to_world(input.aim);
+                self.player.rotation = atan2f(aim.y - self.player.pos.y, aim.x - self.player.pos.x) - PI / 2f32;
+
+                //move bullets
+                for bullet in &mut self.bullets{
+                    bullet.health -= 1;
+                    if bullet.cooldown != 0 {
+                        bullet.cooldown -= 1;
+                    }
+                    Self::fly(bullet, BULLET_WIDTH / 2f32, solids);
+                }
+
+                //move particles
+                for particle in &mut self.particles {
+                    particle.pos += particle.d;
+                    particle.health -= 1;
+                    particle.d.x /= 1.1f32;
+                    particle.d.y /= 1.1f32;
+                }
+
+                //move enemies towards player
+                self.move_enemies();
+
+                //move spit
+                for spit in &mut self.spit {
+                    spit.health -= 1;
+                    Self::fly(spit, BULLET_WIDTH / 2f32, solids);
+                }
+
+                self.handle_collisions();
+                self.advance_pickups();
+
+                //clear bullets
+                self.clear_entities();
+
+                //reloading
+                if self.reloading != 0 {
+                    self.reloading -= 1;
+                }
+
+                self.advance_reload();
+                if input.reload {
+                    self.start_reload();
+                }
+
+                //if space is currently pressed, fire shot.
+                if input.fire && self.reloading == 0 {
+                    self.fire_shot();
+                }
+                self.advance_waves();
+
+                self.stats.ticks_survived += 1;
+                if self.config.adaptive_difficulty && self.stats.ticks_survived.is_multiple_of(TICKS_PER_SECOND as u64) {
+                    let performance = Performance {
+                        health: self.player.health as f32 / self.max_health as f32,
+                        kills: self.stats.kills,
+                        shots: self.stats.shots,
+                        hits: self.stats.hits,
+                    };
+                    self.difficulty.sample(performance, self.config.intensity_low, self.config.intensity_high);
+                }
+                if self.player.health == 0 {
+                    self.state = State::GameOver;
+                }
+            }
+            State::Paused => {
+                self.reloading = 179;
+            }
+            State::Unpausing => {
+                self.reloading -= 1;
+                if self.reloading == 0{
+                    self.state = State::Playing;
+                }
+            }
+            State::GameOver => (),
+        }
+
+        self.tick += 1;
+    }
+
+    /// Hands over everything that happened since the last call.
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, WorldEvent> {
+        self.events.drain(..)
+    }
+
+    /// What the player can see right now.
+    pub fn sight(&self) -> Sight {
+        let facing = vec_from_angle(-self.player.rotation);
+        Sight {
+            origin: self.player.pos,
+            facing: facing.y.atan2(facing.x),
+            half_angle: self.config.vision_angle / 2f32,
+            range: self.config.vision_range,
+            ambient: self.config.ambient_radius,
+        }
+    }
+
+    /// Guns can be bought while paused and between two waves.
+    pub fn shop_open(&self) -> bool {
+        //the delay before the first wave is too short to shop in
+        self.state == State::Paused || (self.director.in_intermission() && self.director.wave > 1)
+    }
+
+    fn advance_waves(&mut self){
+        let data = Rc::clone(&self.data);
+        let adaptive_scale = if self.config.adaptive_difficulty { self.difficulty.interval_scale() } else { 1f32 };
+        let interval_scale = self.config.spawn_interval_scale * adaptive_scale;
+        match self.director.step(&data.waves, self.enemies.len(), interval_scale) {
+            DirectorStep::Idle => (),
+            DirectorStep::Started => self.events.push(WorldEvent::WaveStarted(self.director.wave)),
+            DirectorStep::Spawn => self.spawn_enemy(),
+            DirectorStep::Cleared => {
+                self.stats.waves_cleared += 1;
+                self.restock();
+                self.events.push(WorldEvent::WaveCleared(self.director.wave - 1));
+            }
+        }
+    }
+
+    /// Buys the gun in `slot` if it isn't owned yet, switches to it otherwise.
+    fn select_gun(&mut self, slot: usize) {
+        if slot >= self.owned_guns.len() {
+            return;
+        }
+        if !self.owned_guns[slot]{
+            let price = self.data.weapons.get(slot).price;
+            if self.dollars >= price{
+                self.dollars -= price;
+                self.owned_guns[slot] = true;
+            }
+        }
+        else if slot != self.using_gun{
+            //a reload doesn't carry over to the other gun
+            self.reload_left = 0;
+            self.using_gun = slot;
+        }
+    }
+
+    fn start_reload(&mut self) {
+        let weapon = self.data.weapons.get(self.using_gun);
+        let ammo = &self.ammo[self.using_gun];
+        if self.reload_left == 0 && ammo.magazine < weapon.magazine && ammo.reserve != Some(0) {
+            self.reload_left = self.config.reload_time(weapon);
+        }
+    }
+
+    fn advance_reload(&mut self) {
+        if self.reload_left == 0 {
+            return;
+        }
+        self.reload_left -= 1;
+        if self.reload_left == 0 {
+            let size = self.data.weapons.get(self.using_gun).magazine;
+            let ammo = &mut self.ammo[self.using_gun];
+            let missing = size - ammo.magazine;
+            let taken = match &mut ammo.reserve {
+                Some(reserve) => {
+                    let taken = missing.min(*reserve);
+                    *reserve -= taken;
+                    taken
+                }
+                None => missing,
+            };
+            ammo.magazine += taken;
+        }
+    }
+
+    fn fire_shot(&mut self){
+        let data = Rc::clone(&self.data);
+        let weapon = data.weapons.get(self.using_gun);
+
+        let ammo = &mut self.ammo[self.using_gun];
+        if ammo.magazine == 0 {
+            //click once per fire interval, and not at all while reloading
+            if self.reload_left == 0 {
+                self.reloading = self.config.fire_interval(weapon);
+                self.events.push(WorldEvent::EmptyClick(weapon.empty_sound));
+            }
+            return;
+        }
+        ammo.magazine -= 1;
+        //shooting what's left in the magazine interrupts a reload
+        self.reload_left = 0;
+
+        for _ in 0..weapon.projectiles{
+            let x = self.player.pos.x;
+            let y = self.player.pos.y;
+            //random in 20 degrees cone:
+            let randf = self.rng.gen::<f32>() * weapon.spread - weapon.spread / 2f32;
+            let rot = self.player.rotation + randf;
+            let dir = vec_from_angle(-rot);
+            let pos = Vec2::new(x + dir.x * (BULLET_HEIGHT + PLAYER_HEIGHT) / 2f32, y + dir.y * (BULLET_HEIGHT + PLAYER_HEIGHT) / 2f32);
+            let new_bullet = Entity{
+                d: Vec2::new(dir.x * weapon.bullet_speed, dir.y * weapon.bullet_speed),
+                rotation: rot,
+                health: weapon.lifetime,
+                damage: weapon.damage,
+                knockback: weapon.knockback,
+                ..Entity::new(EntityTypes::Bullet, pos, data.sprites.bullet)
+            };
+            self.bullets.push(new_bullet);
+            self.stats.shots += 1;
+        }
+        let fire_interval = self.config.fire_interval(weapon);
+        self.reloading = if self.power_ups[PowerUp::RapidFire] > 0 { (fire_interval / 2).max(1) } else { fire_interval };
+        let muzzle = self.player.pos + vec_from_angle(-self.player.rotation) * (BULLET_HEIGHT + PLAYER_HEIGHT) / 2f32;
+        self.events.push(WorldEvent::ShotFired(weapon.sound, muzzle));
+    }
+
+    fn spawn_enemy(&mut self){
+        //pick one of the kinds of the current wave, weighted
+        let data = Rc::clone(&self.data);
+        let plan = data.waves.plan(self.director.wave);
+        let id = weighted(&mut self.rng, plan.enemies);
+
+        let area = data.level.spawns[plan.edges[self.rng.gen_range(0..plan.edges.len())]].bounds;
+        let pos = area.min + area.size() * Vec2::new(self.rng.gen::<f32>(), self.rng.gen::<f32>());
+        self.add_enemy(id, pos);
+    }
+
+    /// Adds an enemy of kind `id`, made tougher by how far the waves got.
+    fn add_enemy(&mut self, id: EnemyKindId, pos: Vec2){
+        let kind = self.data.enemies.get(id);
+        let clip = self.data.clips.get(kind.clip);
+        let plan = self.data.waves.plan(self.director.wave);
+        let speed = (self.config.enemy_speed(kind) * plan.speed).min(MAX_SPEED);
+        let rot = atan2f(self.player.pos.y - pos.y, self.player.pos.x - pos.x) - PI / 2f32;
+        let new_enemy = Entity{
+            d: vec_from_angle(-rot) * speed,
+            rotation: rot,
+            health: ((kind.health as f32 * plan.health).round() as u16).clamp(1, MAX_HEALTH),
+            damage: kind.damage,
+            speed,
+            animation: Some(Animation::new(kind.clip, &self.data.clips)),
+            kind: Some(id),
+            ..Entity::new(EntityTypes::Enemy, pos, clip.image)
+        };
+        self.enemies.push(new_enemy);
+    }
+
+    fn move_enemies(&mut self){
+        let data = Rc::clone(&self.data);
+        let speed_scale = if self.config.adaptive_difficulty { self.difficulty.speed_scale() } else { 1f32 };
+        //every enemy steers by where the others were at the start of the tick
+        let tree = self.enemy_tree();
+        let before: Vec<(Vec2, Vec2)> = self.enemies.iter().map(|enemy| (enemy.pos, enemy.d)).collect();
+        let radius = self.config.neighbour_radius;
+        let mut neighbours: Vec<usize> = Vec::new();
+        for (index, enemy) in self.enemies.iter_mut().enumerate(){
+            let kind = data.enemies.get(enemy.kind.unwrap());
+            enemy.rotation = atan2f(self.player.pos.y - enemy.pos.y, self.player.pos.x - enemy.pos.x) - PI / 2f32;
+            let half = Vec2::splat(ENEMY_WIDTH * kind.scale / 2f32);
+            enemy.pos = move_and_slide(enemy.pos, half, enemy.d, &data.level.solids).0;
+            let dir = vec_from_angle(-enemy.rotation);
+            let to_player = distance(&self.player, enemy);
+
+            let (pos, _) = before[index];
+            let bound = aabb2(pos.x - radius, pos.y - radius, pos.x + radius, pos.y + radius);
+            let mut visitor = DiscreteVisitor::<Aabb2<f32>, Value>::new(&bound);
+            neighbours.clear();
+            neighbours.extend(tree.query(&mut visitor).into_iter()
+                .map(|(other, _)| other.index as usize)
+                .filter(|other| *other != index && before[*other].0.distance(pos) < radius));
+
+            //spitters keep a bit inside their range and shoot from there
+            let target = match kind.behaviour {
+                Behaviour::Spit(spit) => self.player.pos - dir * spit.range * 0.8,
+                _ => self.player.pos,
+            };
+            let speed = enemy.speed * speed_scale;
+            let desired = steering::arrive(enemy.pos, target, speed, self.config.arrival_radius);
+            let separation = steering::separation(neighbours.iter().map(|other| pos - before[*other].0), radius);
+            let headings: Vec<Vec2> = neighbours.iter().map(|other| before[*other].1).collect();
+            let alignment = steering::alignment(enemy.d, &headings);
+            let force = (desired - enemy.d)
+                + separation * self.config.separation_weight * speed
+                + alignment * self.config.alignment_weight;
+            enemy.d = (enemy.d + force.clamp_length_max(self.config.steering_force)).clamp_length_max(speed);
+            if enemy.flash != 0 {
+                enemy.flash -= 1;
+            }
+            if enemy.cooldown != 0 {
+                enemy.cooldown -= 1;
+            }
+            if let Behaviour::Spit(spit) = kind.behaviour {
+                if enemy.cooldown == 0 && to_player < spit.range {
+                    enemy.cooldown = spit.interval;
+                    let new_spit = Entity{
+                        d: dir * spit.speed,
+                        rotation: enemy.rotation,
+                        health: (spit.range / spit.speed) as u16 + 1,
+                        damage: spit.damage,
+                        ..Entity::new(EntityTypes::Spit, enemy.pos, data.sprites.bullet)
+                    };
+                    self.spit.push(new_spit);
+                }
+            }
+        }
+    }
+
+    fn clear_entities(&mut self) {
+
+        self.bullets.retain(
+            |bullet|
+            bullet.health > 0
+        );
+        self.enemies.retain(
+            |enemy|
+            enemy.health > 0
+        );
+        self.spit.retain(
+            |spit|
+            spit.health > 0
+        );
+        self.particles.retain(
+            |enemy|
+            enemy.health > 0
+        );
+    }
+
+    /// Moves a bullet or spit along its path, stopping it at the first solid in the way.
+    fn fly(projectile: &mut Entity, half: f32, solids: &[Aabb]){
+        match sweep(projectile.pos, Vec2::splat(half), projectile.d, solids) {
+            Some((fraction, _)) => {
+                projectile.pos += projectile.d * fraction;
+                projectile.health = 0;
+            }
+            None => projectile.pos += projectile.d,
+        }
+    }
+
+    /// Bounding volume tree of the enemies where they stand now, for finding
+    /// what touches what.
+    fn enemy_tree(&self) -> DynamicBoundingVolumeTree<Value>{
+        let mut tree = DynamicBoundingVolumeTree::<Value>::new();
+        for i in 0..self.enemies.len() {
+            let enemy = self.enemies.get(i).unwrap();
+            let half = ENEMY_WIDTH * self.data.enemies.get(enemy.kind.unwrap()).scale / 2f32;
+            let minx = enemy.pos.x - half;
+            let miny = enemy.pos.y - half;
+            let maxx = enemy.pos.x + half;
+            let maxy = enemy.pos.y + half;
+            tree.insert(Value::new(aabb2(minx, miny, maxx, maxy), i as u16));
+        }
+        tree.tick();
+        tree
+    }
+
+    fn handle_collisions(&mut self){
+
+        let data = Rc::clone(&self.data);
+        let tree = self.enemy_tree();
+        let piercing = self.power_ups[PowerUp::Piercing] > 0;
+        let invulnerable = self.power_ups[PowerUp::Invulnerable] > 0;
+
+        //enemies that died this tick, and whether the player killed them
+        let mut deaths: Vec<(usize, bool)> = Vec::new();
+        let mut splatters: Vec<(Vec2, f32, u16)> = Vec::new();
+
+        for bullet in &mut self.bullets{
+            let minx = bullet.pos.x - BULLET_WIDTH / 2f32;
+            let miny = bullet.pos.y - BULLET_WIDTH / 2f32;
+            let maxx = bullet.pos.x + BULLET_WIDTH / 2f32;
+            let maxy = bullet.pos.y + BULLET_WIDTH / 2f32;
+
+            let bound = aabb2(minx, miny, maxx, maxy);
+            let mut visitor = DiscreteVisitor::<Aabb2<f32>, Value>::new(&bound);
+            let result = tree.query(&mut visitor);
+            //a piercing bullet hurts everything it touches this tick, then
+            //nothing until it is through
+            let ready = bullet.cooldown == 0;
+            for enemy in result{
+                let enemy = enemy.0;
+                if ready && enemy.center().distance(bullet.pos) <= enemy.radius() {
+                    let mut killed = false;
+                    if let Some(certain_enemy) = self.enemies.get_mut(enemy.index as usize) {
+                        //an enemy hit by several bullets in one tick only dies once
+                        if certain_enemy.health > 0 {
+                            certain_enemy.health = certain_enemy.health.saturating_sub(bullet.damage);
+                            certain_enemy.flash = HIT_FLASH_TIME;
+                            let half = Vec2::splat(ENEMY_WIDTH * data.enemies.get(certain_enemy.kind.unwrap()).scale / 2f32);
+                            let push = bullet.d.normalize_or_zero() * bullet.knockback;
+                            certain_enemy.pos = move_and_slide(certain_enemy.pos, half, push, &data.level.solids).0;
+                            self.stats.hits += 1;
+                            killed = certain_enemy.health == 0;
+                        }
+                    }
+                    if killed {
+                        deaths.push((enemy.index as usize, true));
+                    }
+                    if piercing {
+                        bullet.cooldown = PIERCE_GRACE;
+                    }
+                    else {
+                        bullet.health = 0;
+                    }
+                    //a kill splatters more than a hit
+                    let particles = if killed { 5 } else { 2 };
+                    splatters.push((bullet.pos + bullet.d, bullet.rotation, particles));
+                }
+            }
+        }
+
+        for (pos, rotation, count) in splatters {
+            self.splatter(pos, rotation, self.config.particle_angle, count);
+        }
+
+        let minx = self.player.pos.x - PLAYER_WIDTH / 2f32;
+        let miny = self.player.pos.y - PLAYER_WIDTH / 2f32;
+        let maxx = self.player.pos.x + PLAYER_WIDTH / 2f32;
+        let maxy = self.player.pos.y + PLAYER_WIDTH / 2f32;
+        let bound = aabb2(minx, miny, maxx, maxy);
+        let mut visitor = DiscreteVisitor::<Aabb2<f32>, Value>::new(&bound);
+        let result = tree.query(&mut visitor);
+        for enemy in result{
+            let enemy = enemy.0;
+            if enemy.center().distance(self.player.pos) <= enemy.radius() {
+                if let Some(certain_enemy) = self.enemies.get_mut(enemy.index as usize) {
+                    if certain_enemy.health > 0 {
+                        certain_enemy.health = 0;
+                        if !invulnerable {
+                            self.player.health = self.player.health.saturating_sub(certain_enemy.damage);
+                        }
+                        deaths.push((enemy.index as usize, false));
+                    }
+                }
+            }
+        }
+
+        for spit in &mut self.spit {
+            if spit.pos.distance(self.player.pos) <= PLAYER_WIDTH / 2f32 {
+                spit.health = 0;
+                if !invulnerable {
+                    self.player.health = self.player.health.saturating_sub(spit.damage);
+                }
+            }
+        }
+
+        self.resolve_deaths(deaths);
+    }
+
+    /// Pays out kills, rolls for drops and lets dying enemies explode or split.
+    /// An explosion can kill more enemies, which are resolved in turn.
+    fn resolve_deaths(&mut self, mut deaths: Vec<(usize, bool)>){
+        let data = Rc::clone(&self.data);
+        let invulnerable = self.power_ups[PowerUp::Invulnerable] > 0;
+        while let Some((index, by_player)) = deaths.pop() {
+            let pos = self.enemies[index].pos;
+            let kind = data.enemies.get(self.enemies[index].kind.unwrap());
+            if by_player {
+                self.dollars = self.dollars.saturating_add(kind.reward);
+                self.stats.kills += 1;
+                self.stats.dollars_earned += kind.reward as u32;
+                if !kind.drops.is_empty() && self.rng.gen_bool(kind.drop_chance as f64) {
+                    let pickup = weighted(&mut self.rng, &kind.drops);
+                    self.drop_pickup(pickup, pos);
+                }
+            }
+            match kind.behaviour {
+                Behaviour::Explode(explode) => {
+                    if pos.distance(self.player.pos) <= explode.radius && !invulnerable {
+                        self.player.health = self.player.health.saturating_sub(explode.damage);
+                    }
+                    for (other_index, other) in self.enemies.iter_mut().enumerate() {
+                        if other.health > 0 && other.pos.distance(pos) <= explode.radius {
+                            other.health = other.health.saturating_sub(explode.damage);
+                            other.flash = HIT_FLASH_TIME;
+                            if other.health == 0 {
+                                deaths.push((other_index, by_player));
+                            }
+                        }
+                    }
+                    self.splatter(pos, 0f32, 2f32 * PI, 16);
+                    self.events.push(WorldEvent::Exploded { pos, radius: explode.radius });
+                }
+                //only falls apart when shot, not when it reached the player
+                Behaviour::Split { into, count } if by_player => {
+                    for _ in 0..count {
+                        let offset = Vec2::new(self.rng.gen_range(-20f32..=20f32), self.rng.gen_range(-20f32..=20f32));
+                        self.add_enemy(into, pos + offset);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn drop_pickup(&mut self, kind: PickupKindId, pos: Vec2){
+        let lifetime = self.data.pickups.get(kind).lifetime;
+        self.pickups.push(Pickup { kind, pos, ticks_left: lifetime });
+    }
+
+    /// Puts a fresh pickup on every pickup spot of the level that has none
+    /// lying on it.
+    fn restock(&mut self){
+        let data = Rc::clone(&self.data);
+        for (kind, pos) in &data.level.pickups {
+            if !self.pickups.iter().any(|pickup| pickup.kind == *kind && pickup.pos.distance(*pos) <= PICKUP_RADIUS) {
+                self.drop_pickup(*kind, *pos);
+            }
+        }
+    }
+
+    /// Counts down the power-ups and the pickups lying around, pulls in the
+    /// pickups near the player and picks up those within reach.
+    fn advance_pickups(&mut self){
+        let data = Rc::clone(&self.data);
+        for ticks in self.power_ups.values_mut() {
+            *ticks = ticks.saturating_sub(1);
+        }
+        //ammo nobody can use stays on the ground for later
+        let wants_ammo = self.ammo_slot().is_some();
+        let mut picked = Vec::new();
+        for pickup in &mut self.pickups {
+            pickup.ticks_left -= 1;
+            if matches!(data.pickups.get(pickup.kind).effect, Effect::Ammo(_)) && !wants_ammo {
+                continue;
+            }
+            let offset = self.player.pos - pickup.pos;
+            if offset.length() <= self.config.magnet_radius {
+                pickup.pos = move_and_slide(pickup.pos, Vec2::ZERO, offset.clamp_length_max(self.config.magnet_speed), &data.level.solids).0;
+            }
+            if pickup.pos.distance(self.player.pos) <= PICKUP_RADIUS {
+                pickup.ticks_left = 0;
+                picked.push(pickup.kind);
+            }
+        }
+        self.pickups.retain(|pickup| pickup.ticks_left > 0);
+        for kind in picked {
+            self.pick_up(kind);
+        }
+    }
+
+    fn pick_up(&mut self, id: PickupKindId){
+        let data = Rc::clone(&self.data);
+        let kind = data.pickups.get(id);
+        match kind.effect {
+            Effect::Cash(dollars) => {
+                self.dollars = self.dollars.saturating_add(dollars);
+                self.stats.dollars_earned += dollars as u32;
+            }
+            Effect::Health(health) => self.player.health = self.player.health.saturating_add(health).min(self.max_health),
+            Effect::Ammo(magazines) => {
+                if let Some(slot) = self.ammo_slot() {
+                    let size = data.weapons.get(slot).magazine;
+                    let reserve = self.ammo[slot].reserve.as_mut().unwrap();
+                    *reserve = reserve.saturating_add(magazines.saturating_mul(size));
+                }
+            }
+            //picking up one that is active already tops it up
+            Effect::PowerUp(power_up, duration) => self.power_ups[power_up] = self.power_ups[power_up].max(duration),
+        }
+        self.stats.pickups += 1;
+        self.events.push(WorldEvent::PickedUp(kind.sound));
+    }
+
+    /// Slot of the gun ammo pickups go to: the one in hand, or else the first
+    /// owned one with limited spare rounds. `None` when every owned gun has
+    /// unlimited spare rounds.
+    fn ammo_slot(&self) -> Option<usize> {
+        let limited = |slot: &usize| self.owned_guns[*slot] && self.ammo[*slot].reserve.is_some();
+        Some(self.using_gun).filter(limited).or_else(|| (0..self.owned_guns.len()).find(limited))
+    }
+
+    /// Sprays `count` blood particles from `pos`, in a `cone` wide around `rotation`.
+    fn splatter(&mut self, pos: Vec2, rotation: f32, cone: f32, count: u16){
+        for _ in 0..count{
+
+            //random in 20 degrees cone:
+            let randf = self.rng.gen::<f32>() * cone - cone / 2f32;
+            let rot = rotation + randf;
+            let dir = vec_from_angle(-rot) * 5f32;
+
+            let new_particle = Entity{
+                d: dir,
+                health: self.config.particle_health,
+                rotation: rot,
+                ..Entity::new(EntityTypes::Particle, pos, self.data.sprites.blood_particle)
+            };
+
+            self.particles.push(new_particle);
+        }
+    }
+
+    fn advance_frames(&mut self, entity: EntityTypes, input: &Input){
+        let data = Rc::clone(&self.data);
+        match entity{
+            EntityTypes::Player => {
+                let clip = if input.is_moving() { data.core_clips.player_walk } else { data.core_clips.player_idle };
+                let animation = self.player.animation.as_mut().unwrap();
+                animation.play(clip, &data.clips);
+                let events = animation.advance(&data.clips);
+                self.events.extend(events.cloned().map(WorldEvent::Animation));
+            }
+            EntityTypes::Enemy => {
+                //enemies out of sight make no sound but their footsteps
+                let sight = self.sight();
+                let hearing = self.config.hearing_distance;
+                let mut nearest_step: Option<(f32, SoundHandle)> = None;
+                for enemy in &mut self.enemies {
+                    let Some(animation) = &mut enemy.animation else { continue };
+                    let seen = sight.sees(enemy.pos, &data.level.solids);
+                    let dist = enemy.pos.distance(self.player.pos);
+                    for event in animation.advance(&data.clips) {
+                        if event.name != FOOTSTEP {
+                            if seen {
+                                self.events.push(WorldEvent::Animation(event.clone()));
+                            }
+                        } else if let Some(sound) = event.sound.filter(|_| dist < hearing && nearest_step.is_none_or(|(nearest, _)| dist < nearest)) {
+                            nearest_step = Some((dist, sound));
+                        }
+                    }
+                }
+                if let Some((dist, sound)) = nearest_step {
+                    self.events.push(WorldEvent::Footstep { sound, loudness: 1f32 - dist / hearing });
+                }
+            }
+
+            _ => (),
+        }
+    }
+}
+
+/// One of `choices` picked at random, each as likely as its weight says.
+fn weighted<T: Copy>(rng: &mut ChaCha8Rng, choices: &[(T, u32)]) -> T {
+    let total: u32 = choices.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.gen_range(0..total);
+    let (choice, _) = choices.iter()
+        .find(|(_, weight)| {
+            if roll < *weight {
+                return true;
+            }
+            roll -= weight;
+            false
+        })
+        .unwrap();
+    *choice
+}
+
+fn aabb2(minx: f32, miny: f32, maxx: f32, maxy: f32) -> Aabb2<f32> {
+    Aabb2::new(Point2::new(minx, miny), Point2::new(maxx, maxy))
+}
+
+#[derive(Clone)]
+struct Value {
+    pub aabb: Aabb2<f32>,
+    fat_aabb: Aabb2<f32>,
+    index: u16,
+}
+
+impl Value {
+    pub fn new(aabb: Aabb2<f32>, pos: u16) -> Self {
+        Self {
+            fat_aabb : aabb,
+            aabb,
+            index: pos
+        }
+    }
+
+    fn center(&self) -> Vec2 {
+        Vec2::new(self.aabb.min.x + self.aabb.max.x, self.aabb.min.y + self.aabb.max.y) / 2f32
+    }
+
+    fn radius(&self) -> f32 {
+        (self.aabb.max.x - self.aabb.min.x) / 2f32
+    }
+}
+
+impl TreeValue for Value {
+    type Bound = Aabb2<f32>;
+
+    fn bound(&self) -> &Aabb2<f32> {
+        &self.aabb
+    }
+
+    fn get_bound_with_margin(&self) -> Aabb2<f32> {
+        self.fat_aabb
+    }
+}